@@ -1,9 +1,19 @@
 use crate::{bevy::prelude::*};
+use crate::comp::{physics, stats};
+use crate::{res, sprite_sheet};
 use std::collections::HashMap;
 
+const DEFAULT_FRAME_DURATION: f32 = 0.1;
+const RUN_VELOCITY_THRESHOLD: f32 = 8.;
+
 pub enum AnimCommonState {
     Idle,
     Run,
+    Jump,
+    Fall,
+    WallSlide,
+    Land,
+    Shoot,
 }
 
 impl AnimStateDescriptor for AnimCommonState {
@@ -11,33 +21,109 @@ impl AnimStateDescriptor for AnimCommonState {
         match self {
             Self::Idle => "idle",
             Self::Run => "run",
+            Self::Jump => "jump",
+            Self::Fall => "fall",
+            Self::WallSlide => "wall_slide",
+            Self::Land => "land",
+            Self::Shoot => "shoot",
         }
     }
 }
 
+impl AnimCommonState {
+    fn is_airborne(name: &str) -> bool {
+        name == Self::Jump.name() || name == Self::Fall.name() || name == Self::WallSlide.name()
+    }
+}
+
 pub trait AnimStateDescriptor {
     fn name(&self) -> &str;
 }
 
+/// Animation clips and the currently playing clip of an entity
 pub struct Animation {
     data: HashMap<String, AnimData>,
     current_anim: String,
+    timer: Timer,
+    /// Frame events reached since the last update
+    pending_events: Vec<String>,
 }
 
 impl Animation {
     pub fn new(data: Vec<AnimData>, start_anim: &str) -> Self {
-        Self {
+        let mut animation = Self {
             data: data.into_iter().map(|x| (x.get_name(), x)).into_iter().collect(),
             current_anim: start_anim.to_string(), 
+            timer: Timer::from_seconds(DEFAULT_FRAME_DURATION, true),
+            pending_events: Vec::new(),
+        };
+
+        if let Some(frame_duration) = animation.get_current_data().map(|data| data.get_frame_duration()) {
+            animation.timer.duration = frame_duration;
         }
+
+        animation
     }
 
     fn get_current_data(&mut self) -> Option<&mut AnimData> {
         self.data.get_mut(&self.current_anim)
     }
 
+    pub fn current_anim(&self) -> &str {
+        &self.current_anim
+    }
+
+    /// Is a one shot clip currently playing
+    pub fn is_playing_once(&self) -> bool {
+        match self.data.get(&self.current_anim) {
+            Some(data) => data.fallback.is_some(),
+            None => false,
+        }
+    }
+
     pub fn set_anim(&mut self, name: &str) {
+        if self.current_anim == name || !self.data.contains_key(name) {
+            return;
+        }
+
         self.current_anim = name.to_string();
+        if let Some(data) = self.get_current_data() {
+            data.current_index = 0;
+            let frame_duration = data.get_frame_duration();
+            let events = data.get_frame_events();
+
+            self.timer.reset();
+            self.timer.duration = frame_duration;
+            self.pending_events.extend(events);
+        }
+    }
+
+    /// Take the frame events reached since last call
+    pub fn drain_events(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.pending_events)
+    }
+
+    /// Step the current clip and return the sprite index to show
+    fn update(&mut self, delta_seconds: f32) -> Option<u32> {
+        self.timer.tick(delta_seconds);
+        let frame_finished = self.timer.finished;
+
+        let data = self.get_current_data()?;
+        if !frame_finished {
+            return Some(data.get_index());
+        }
+
+        if data.next_frame() {
+            let events = data.get_frame_events();
+
+            self.timer.duration = data.get_frame_duration();
+            self.pending_events.extend(events);
+        } else if let Some(fallback) = data.fallback.clone() {
+            // One shot clip is done, hand over to the fallback clip
+            self.set_anim(&fallback);
+        }
+
+        self.get_current_data().map(|data| data.get_index())
     }
 }
 
@@ -46,6 +132,12 @@ pub struct AnimData {
     start_index: u32,
     frames_count: u32,
     current_index: u32,
+    /// Duration of each frame, the last one is used for any remaining frames
+    frame_durations: Vec<f32>,
+    /// Clip to play when a one shot clip ends, looping clips have none
+    fallback: Option<String>,
+    /// Events emitted when reaching a frame, frames are relative to the clip
+    frame_events: Vec<(u32, String)>,
 }
 
 impl AnimData {
@@ -55,34 +147,161 @@ impl AnimData {
             start_index,
             frames_count,
             current_index: 0,
+            frame_durations: vec![DEFAULT_FRAME_DURATION],
+            fallback: None,
+            frame_events: Vec::new(),
         }
     }
 
+    /// Clip that plays once and then switches to `fallback`
+    pub fn one_shot(name: &str, start_index: u32, frames_count: u32, fallback: &str) -> Self {
+        Self {
+            fallback: Some(fallback.to_string()),
+            ..Self::new(name, start_index, frames_count)
+        }
+    }
+
+    pub fn with_frame_duration(mut self, frame_duration: f32) -> Self {
+        self.frame_durations = vec![frame_duration];
+        self
+    }
+
+    pub fn with_frame_durations(mut self, frame_durations: Vec<f32>) -> Self {
+        if !frame_durations.is_empty() {
+            self.frame_durations = frame_durations;
+        }
+        self
+    }
+
+    pub fn with_frame_event(mut self, frame: u32, name: &str) -> Self {
+        self.frame_events.push((frame, name.to_string()));
+        self
+    }
+
     fn get_name(&self) -> String {
         self.name.clone()
     }
 
-    fn get_index(&mut self) -> u32 {
-        self.current_index += 1;
-        if self.current_index >= self.frames_count {
-            self.current_index = 0;
+    fn get_index(&self) -> u32 {
+        self.start_index + self.current_index
+    }
+
+    fn get_frame_events(&self) -> Vec<String> {
+        self.frame_events
+            .iter()
+            .filter(|(frame, _)| *frame == self.current_index)
+            .map(|(_, name)| name.clone())
+            .collect()
+    }
+
+    fn get_frame_duration(&self) -> f32 {
+        self.frame_durations
+            .get(self.current_index as usize)
+            .or_else(|| self.frame_durations.last())
+            .cloned()
+            .unwrap_or(DEFAULT_FRAME_DURATION)
+    }
+
+    /// Move to the next frame, returns false when a one shot clip
+    /// has no frames left
+    fn next_frame(&mut self) -> bool {
+        if self.current_index + 1 < self.frames_count {
+            self.current_index += 1;
+            return true;
         }
-        
-        let i = self.frames_count - self.current_index;
-        (i % self.frames_count) + self.start_index
+
+        if self.fallback.is_some() {
+            return false;
+        }
+
+        self.current_index = 0;
+        true
     }
 }
 
 fn animate_sprite_system(
-    mut animation: ResMut<Animation>,
-    mut query: Query<(&mut Timer, &mut TextureAtlasSprite)>,
+    state: Res<res::GameState>,
+    time: Res<Time>,
+    mut animation_events: ResMut<Events<res::AnimationEvent>>,
+    mut query: Query<(Entity, &mut Animation, &mut TextureAtlasSprite)>,
 ) {
-    if let Some(animation_data) = animation.get_current_data() {
-        for (timer, mut sprite) in query.iter_mut() {
-            if timer.finished {
-                sprite.index = animation_data.get_index();
-            }
+    if !state.is_playing() {
+        return;
+    }
+
+    for (entity, mut animation, mut sprite) in query.iter_mut() {
+        if let Some(index) = animation.update(time.delta_seconds) {
+            sprite.index = index;
+        }
+
+        for name in animation.drain_events() {
+            animation_events.send(res::AnimationEvent { entity, name });
+        }
+    }
+}
+
+/// Transition between animation states from the physics state of an entity
+fn anim_state_system(
+    state: Res<res::GameState>,
+    mut query: Query<(
+        &mut Animation,
+        &physics::Velocity,
+        &stats::Grounded,
+        &physics::CollisionData,
+    )>,
+) {
+    if !state.is_playing() {
+        return;
+    }
+
+    for (mut animation, velocity, grounded, collision_data) in query.iter_mut() {
+        if let Some(next) = next_anim_state(&animation, velocity, grounded, collision_data) {
+            animation.set_anim(next.name());
+        }
+    }
+}
+
+fn next_anim_state(
+    animation: &Animation,
+    velocity: &physics::Velocity,
+    grounded: &stats::Grounded,
+    collision_data: &physics::CollisionData,
+) -> Option<AnimCommonState> {
+    let current = animation.current_anim();
+    let was_airborne = AnimCommonState::is_airborne(current);
+
+    if !grounded.0 || !collision_data.below {
+        if collision_data.either_side() && velocity.y() <= 0. {
+            return Some(AnimCommonState::WallSlide);
+        }
+
+        // Let the jump clip play out, it falls back to the fall clip
+        if current == AnimCommonState::Jump.name() {
+            return None;
         }
+
+        if velocity.y() > 0. && (!was_airborne || current == AnimCommonState::WallSlide.name()) {
+            return Some(AnimCommonState::Jump);
+        }
+
+        return Some(AnimCommonState::Fall);
+    }
+
+    if was_airborne {
+        return Some(AnimCommonState::Land);
+    }
+
+    // Don't cut landing or shooting short while on the ground
+    if animation.is_playing_once() {
+        return None;
+    }
+
+    // TODO: - Figure out a way to detect riding on moving objects should work
+    // with updating animations
+    if velocity.x().abs() > RUN_VELOCITY_THRESHOLD {
+        Some(AnimCommonState::Run)
+    } else {
+        Some(AnimCommonState::Idle)
     }
 }
 
@@ -90,17 +309,11 @@ pub struct AnimationPlugin;
 
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_resource(
-            Animation::new(
-                vec!{
-                    AnimData::new(AnimCommonState::Idle.name(), 0, 4),
-                    AnimData::new(AnimCommonState::Run.name(), 10, 5),
-                },
-                AnimCommonState::Idle.name()
-            )
-        )
-        .add_system(animate_sprite_system.system())
-        .add_system(animate_lava_system.system());
+        app.add_event::<res::AnimationEvent>()
+            .add_plugin(sprite_sheet::SpriteSheetPlugin)
+            .add_system(anim_state_system.system())
+            .add_system(animate_sprite_system.system())
+            .add_system(animate_lava_system.system());
     }
 }
 
@@ -129,4 +342,44 @@ fn animate_lava_system(
             sprite.index = lava.data.get_next_index();
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looping_clip_wraps_to_its_first_frame() {
+        let mut data = AnimData::new("run", 10, 2);
+
+        assert!(data.next_frame());
+        assert_eq!(data.get_index(), 11);
+        assert!(data.next_frame());
+        assert_eq!(data.get_index(), 10);
+    }
+
+    #[test]
+    fn one_shot_clip_ends_on_its_last_frame() {
+        let mut data = AnimData::one_shot("land", 24, 2, "idle");
+
+        assert!(data.next_frame());
+        assert!(!data.next_frame());
+        assert_eq!(data.get_index(), 25);
+    }
+
+    #[test]
+    fn finished_one_shot_hands_over_to_its_fallback() {
+        let mut animation = Animation::new(
+            vec![
+                AnimData::new("idle", 0, 4),
+                AnimData::one_shot("shoot", 2, 1, "idle").with_frame_duration(0.1),
+            ],
+            "idle",
+        );
+        animation.set_anim("shoot");
+        assert!(animation.is_playing_once());
+
+        assert_eq!(animation.update(0.1), Some(0));
+        assert_eq!(animation.current_anim(), "idle");
+    }
+}
@@ -1,27 +1,223 @@
 use bevy::prelude::*;
+use bevy::input::gamepad::Gamepad;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
 #[derive(Debug, Default)]
 pub struct Player {
+    /// Unique per spawned player, keyboard and mouse drive the player with id 0
+    pub id: usize,
+    /// Gamepad driving the player, claimed by pressing a button on a pad no
+    /// player owns yet
+    pub gamepad: Option<Gamepad>,
+}
 
+impl Player {
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            gamepad: None,
+        }
+    }
 }
 
-pub struct Furniture;
+#[derive(Debug, Default)]
+pub struct Furniture {
+    pub shape: FurnitureShape,
+    /// Seconds the player has been standing on it
+    pub stand_time: f32,
+    /// Whether the player landed on it since it last spawned
+    pub landed: bool,
+}
 
-// // Different types of spawning furnitures
-// enum FurnitureShape {
-//     Chair,
-//     Table,
-//     Sofa,
-//     Refrigerator,
-//     TV,
-//     Lamp,
-// }
+impl Furniture {
+    /// Reuse the pooled piece as a freshly spawned one
+    pub fn respawn(&mut self, shape: FurnitureShape) {
+        *self = Self {
+            shape,
+            ..Default::default()
+        };
+    }
 
-#[derive(Debug, Default)]
+    /// Mark the piece as landed on, true only for the first landing since it spawned
+    pub fn land(&mut self) -> bool {
+        !std::mem::replace(&mut self.landed, true)
+    }
+}
+
+// Different types of spawning furnitures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FurnitureShape {
+    Chair,
+    Table,
+    Sofa,
+    Refrigerator,
+    TV,
+    Lamp,
+}
+
+impl Default for FurnitureShape {
+    fn default() -> Self {
+        FurnitureShape::Chair
+    }
+}
+
+impl FurnitureShape {
+    pub const ALL: [FurnitureShape; 6] = [
+        FurnitureShape::Chair,
+        FurnitureShape::Table,
+        FurnitureShape::Sofa,
+        FurnitureShape::Refrigerator,
+        FurnitureShape::TV,
+        FurnitureShape::Lamp,
+    ];
+
+    /// Sprite and collider size
+    pub fn size(&self) -> Vec2 {
+        match self {
+            FurnitureShape::Chair => Vec2::new(60., 120.),
+            FurnitureShape::Table => Vec2::new(110., 110.),
+            FurnitureShape::Sofa => Vec2::new(170., 100.),
+            FurnitureShape::Refrigerator => Vec2::new(70., 170.),
+            FurnitureShape::TV => Vec2::new(80., 130.),
+            FurnitureShape::Lamp => Vec2::new(16., 190.),
+        }
+    }
+
+    /// Relative chance of being picked by a spawner
+    pub fn spawn_weight(&self) -> u32 {
+        match self {
+            FurnitureShape::Chair => 4,
+            FurnitureShape::Table => 3,
+            FurnitureShape::Sofa => 2,
+            FurnitureShape::Refrigerator => 2,
+            FurnitureShape::TV => 2,
+            FurnitureShape::Lamp => 1,
+        }
+    }
+
+    /// Seconds it can be stood on before it topples into the lava
+    pub fn max_stand_time(&self) -> Option<f32> {
+        match self {
+            FurnitureShape::Lamp => Some(0.6),
+            _ => None,
+        }
+    }
+
+    /// Key into `res::ColorMaterialStorage`
+    pub fn material_name(&self) -> &'static str {
+        match self {
+            FurnitureShape::Chair => "Furniture_Chair",
+            FurnitureShape::Table => "Furniture_Table",
+            FurnitureShape::Sofa => "Furniture_Sofa",
+            FurnitureShape::Refrigerator => "Furniture_Refrigerator",
+            FurnitureShape::TV => "Furniture_TV",
+            FurnitureShape::Lamp => "Furniture_Lamp",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            FurnitureShape::Chair => Color::rgb(0.45, 0.3, 0.2),
+            FurnitureShape::Table => Color::rgb(0.55, 0.4, 0.25),
+            FurnitureShape::Sofa => Color::rgb(0.3, 0.35, 0.5),
+            FurnitureShape::Refrigerator => Color::rgb(0.85, 0.85, 0.85),
+            FurnitureShape::TV => Color::rgb(0.1, 0.1, 0.1),
+            FurnitureShape::Lamp => Color::rgb(0.8, 0.7, 0.3),
+        }
+    }
+
+    /// Pick a random shape by spawn weight
+    pub fn pick_random<R: rand::Rng>(rng: &mut R) -> FurnitureShape {
+        let total: u32 = Self::ALL.iter().map(|shape| shape.spawn_weight()).sum();
+        let mut roll = rng.gen_range(0, total);
+
+        for shape in Self::ALL.iter() {
+            if roll < shape.spawn_weight() {
+                return *shape;
+            }
+            roll -= shape.spawn_weight();
+        }
+
+        FurnitureShape::default()
+    }
+}
+
+/// Camera the game world is seen through, used to map the cursor into the world
+pub struct MainCamera;
+
+/// Keeps the camera on the player while staying inside the level
+#[derive(Debug)]
+pub struct CameraFollow {
+    /// Half size of the box around the camera center the player can move in freely
+    pub dead_zone: Vec2,
+    /// How fast the camera catches up, higher is snappier
+    pub smoothing: f32,
+    /// Level area the view has to stay inside, min and max corners
+    pub bounds: (Vec2, Vec2),
+    /// Pins the camera along x, used by the scrolling runner mode
+    pub lock_x: Option<f32>,
+    /// Followed position before shake is applied
+    pub position: Vec2,
+}
+
+impl Default for CameraFollow {
+    fn default() -> Self {
+        Self {
+            dead_zone: Vec2::new(60., 40.),
+            smoothing: 5.,
+            bounds: (Vec2::zero(), Vec2::zero()),
+            lock_x: None,
+            position: Vec2::zero(),
+        }
+    }
+}
+
+/// Trauma based screen shake, trauma goes from 0 to 1 and the shake grows with its square
+#[derive(Debug)]
+pub struct CameraShake {
+    pub trauma: f32,
+    /// Trauma lost per second
+    pub decay: f32,
+    pub max_offset: f32,
+    /// Radians
+    pub max_angle: f32,
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        Self {
+            trauma: 0.,
+            decay: 1.5,
+            max_offset: 12.,
+            max_angle: 0.05,
+        }
+    }
+}
+
+impl CameraShake {
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).min(1.);
+    }
+
+    pub fn get_intensity(&self) -> f32 {
+        self.trauma * self.trauma
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Controller {
+    /// Cursor in window pixels, origin at the bottom left. Left out of
+    /// replays, the world position is what the game reads
+    #[serde(skip)]
+    pub cursor_screen_position: Vec2,
+    /// Cursor in world space, follows the camera
     pub cursor_position: Vec2,
+    /// Aim direction from a stick, takes over from the cursor until the mouse moves
+    pub aim_direction: Option<Vec2>,
     pub movement: Vec2,
+    /// Jump is still held, releasing it early cuts the jump short
+    pub jump_held: bool,
     pub action: VecDeque<ControllerAction>,
 }
 
@@ -31,11 +227,14 @@ impl Controller {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ControllerAction {
     Shoot,
     Jump,
     WallJump,
+    SelectWeapon(WeaponKind),
+    /// Step through the weapon kinds, negative goes backwards
+    CycleWeapon(i32),
 }
 
 pub struct Crosshair {
@@ -49,4 +248,227 @@ pub struct CrosshairController {
 
 pub struct Projectile {
     pub direction: Vec2,
-}
\ No newline at end of file
+    pub kind: WeaponKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WeaponKind {
+    WaterGun,
+    /// Continuous stream of small drops
+    Hose,
+    /// Big arcing blob with a large splash
+    Bucket,
+    /// Freezes lava for longer
+    Ice,
+}
+
+impl Default for WeaponKind {
+    fn default() -> Self {
+        WeaponKind::WaterGun
+    }
+}
+
+impl WeaponKind {
+    pub const ALL: [WeaponKind; 4] = [
+        WeaponKind::WaterGun,
+        WeaponKind::Hose,
+        WeaponKind::Bucket,
+        WeaponKind::Ice,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeaponKind::WaterGun => "Water gun",
+            WeaponKind::Hose => "Hose",
+            WeaponKind::Bucket => "Bucket",
+            WeaponKind::Ice => "Ice shot",
+        }
+    }
+
+    /// Kind `steps` away in `ALL`, wrapping around
+    pub fn cycle(&self, steps: i32) -> WeaponKind {
+        let count = Self::ALL.len() as i32;
+        let index = Self::ALL.iter().position(|kind| kind == self).unwrap_or(0) as i32;
+
+        Self::ALL[(index + steps).rem_euclid(count) as usize]
+    }
+
+    /// Shots per second
+    pub fn fire_rate(&self) -> f32 {
+        match self {
+            WeaponKind::WaterGun => 8.,
+            WeaponKind::Hose => 30.,
+            WeaponKind::Bucket => 1.2,
+            WeaponKind::Ice => 3.,
+        }
+    }
+
+    pub fn spread(&self) -> f32 {
+        match self {
+            WeaponKind::WaterGun => 20.,
+            WeaponKind::Hose => 8.,
+            WeaponKind::Bucket => 0.,
+            WeaponKind::Ice => 5.,
+        }
+    }
+
+    pub fn muzzle_speed(&self) -> f32 {
+        match self {
+            WeaponKind::WaterGun => 200.,
+            WeaponKind::Hose => 260.,
+            WeaponKind::Bucket => 160.,
+            WeaponKind::Ice => 300.,
+        }
+    }
+
+    pub fn cost_per_shot(&self) -> f32 {
+        match self {
+            WeaponKind::WaterGun => 4.,
+            WeaponKind::Hose => 1.,
+            WeaponKind::Bucket => 25.,
+            WeaponKind::Ice => 10.,
+        }
+    }
+
+    pub fn projectile_size(&self) -> f32 {
+        match self {
+            WeaponKind::WaterGun => 5.,
+            WeaponKind::Hose => 3.,
+            WeaponKind::Bucket => 14.,
+            WeaponKind::Ice => 6.,
+        }
+    }
+
+    /// Key into `res::ColorMaterialStorage`
+    pub fn material_name(&self) -> &'static str {
+        match self {
+            WeaponKind::Ice => "Ice",
+            _ => "Projectile",
+        }
+    }
+
+    pub fn splash_particle_count(&self) -> i32 {
+        match self {
+            WeaponKind::WaterGun => 6,
+            WeaponKind::Hose => 2,
+            WeaponKind::Bucket => 24,
+            WeaponKind::Ice => 4,
+        }
+    }
+
+    /// Width of the platform made when hitting lava
+    pub fn platform_width(&self) -> f32 {
+        match self {
+            WeaponKind::WaterGun => 40.,
+            WeaponKind::Hose => 16.,
+            WeaponKind::Bucket => 110.,
+            WeaponKind::Ice => 40.,
+        }
+    }
+
+    /// Seconds the platform made when hitting lava lasts
+    pub fn platform_time(&self) -> f32 {
+        match self {
+            WeaponKind::WaterGun => 3.,
+            WeaponKind::Hose => 1.2,
+            WeaponKind::Bucket => 3.5,
+            WeaponKind::Ice => 7.,
+        }
+    }
+}
+
+/// Water gun of an entity, shots are limited by fire rate and what's left in the tank
+#[derive(Debug)]
+pub struct Weapon {
+    pub kind: WeaponKind,
+    /// Shots per second
+    pub fire_rate: f32,
+    pub cooldown: Timer,
+    /// Max random offset added to the projectile velocity on each axis
+    pub spread: f32,
+    pub muzzle_speed: f32,
+    pub tank: f32,
+    pub tank_capacity: f32,
+    pub cost_per_shot: f32,
+    /// Water refilled per second when not at a refill station
+    pub refill_rate: f32,
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        Self::new(WeaponKind::default())
+    }
+}
+
+impl Weapon {
+    pub fn new(kind: WeaponKind) -> Self {
+        let mut weapon = Self {
+            kind,
+            fire_rate: 0.,
+            cooldown: Timer::from_seconds(0.1, false),
+            spread: 0.,
+            muzzle_speed: 0.,
+            tank: 100.,
+            tank_capacity: 100.,
+            cost_per_shot: 0.,
+            refill_rate: 5.,
+        };
+        weapon.set_kind(kind);
+
+        weapon
+    }
+
+    /// Switch weapon kind, the tank is shared between kinds
+    pub fn set_kind(&mut self, kind: WeaponKind) {
+        self.kind = kind;
+        self.fire_rate = kind.fire_rate();
+        self.spread = kind.spread();
+        self.muzzle_speed = kind.muzzle_speed();
+        self.cost_per_shot = kind.cost_per_shot();
+    }
+
+    pub fn can_fire(&self) -> bool {
+        self.cooldown.finished && self.tank >= self.cost_per_shot
+    }
+
+    /// Use up water and restart the cooldown
+    pub fn fire(&mut self) {
+        self.tank -= self.cost_per_shot;
+        self.cooldown.duration = 1. / self.fire_rate;
+        self.cooldown.reset();
+    }
+
+    pub fn refill(&mut self, amount: f32) {
+        self.tank = (self.tank + amount).min(self.tank_capacity);
+    }
+
+    pub fn get_tank_fraction(&self) -> f32 {
+        self.tank / self.tank_capacity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weapon_cycle_wraps_both_ways() {
+        assert_eq!(WeaponKind::WaterGun.cycle(1), WeaponKind::Hose);
+        assert_eq!(WeaponKind::WaterGun.cycle(-1), WeaponKind::Ice);
+        assert_eq!(WeaponKind::Ice.cycle(1), WeaponKind::WaterGun);
+        assert_eq!(WeaponKind::Hose.cycle(-6), WeaponKind::Ice);
+        assert_eq!(WeaponKind::Bucket.cycle(0), WeaponKind::Bucket);
+    }
+
+    #[test]
+    fn furniture_landing_counts_once_per_spawn() {
+        let mut furniture = Furniture::default();
+        assert!(furniture.land());
+        assert!(!furniture.land());
+
+        // The pool hands the same entity out again as a new piece
+        furniture.respawn(FurnitureShape::Table);
+        assert!(furniture.land());
+        assert!(!furniture.land());
+    }
+}
@@ -69,6 +69,17 @@ impl CollisionData {
         self.left || self.right
     }
 
+    /// Direction of the touched wall along x, 0 when not touching one
+    pub fn wall_side(&self) -> f32 {
+        if self.right {
+            1.
+        } else if self.left {
+            -1.
+        } else {
+            0.
+        }
+    }
+
     pub fn reset(&mut self) {
         self.left = false;
         self.right = false;
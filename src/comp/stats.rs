@@ -15,20 +15,61 @@ pub struct JumpForce(pub f32);
 #[derive(Debug, Default, Properties)]
 pub struct Grounded(pub bool); // Move into physics.rs ? :shrug:
 
+/// Coyote time and jump buffering state, the durations are in `Tuning`
+#[derive(Debug, Default, Properties)]
+pub struct JumpGrace {
+    /// Seconds since the entity last stood on ground
+    pub since_grounded: f32,
+    /// Seconds since a jump press that couldn't be used yet
+    pub buffered: Option<f32>,
+    pub air_jump_used: bool,
+}
+
+/// Grants one extra jump while airborne
+#[derive(Debug, Default, Properties)]
+pub struct DoubleJump;
+
 #[derive(Debug, Default, Properties)]
 pub struct Wall;
 
 #[derive(Debug, Default, Properties)]
 pub struct Ground;
 
+/// Touching an entity with this component kills the player.
+#[derive(Debug, Default, Properties)]
+pub struct Hazard;
+
+/// Refills the water tank of a player standing in it, in water per second
+#[derive(Debug, Default, Properties)]
+pub struct RefillStation(pub f32);
+
+/// Platform of cooled lava
+#[derive(Debug, Default, Properties)]
+pub struct Obsidian;
+
+/// Moved ahead of the view once it has scrolled past, `start_x` is where a run starts
+#[derive(Debug, Default, Properties)]
+pub struct Recyclable {
+    pub start_x: f32,
+}
+
+/// Stays at this x offset from the center of the view
+#[derive(Debug, Default, Properties)]
+pub struct FollowView(pub f32);
+
 #[derive(Debug, Default, Properties)]
 pub struct Facing(pub f32);
 
 #[derive(Debug, Default, Properties)]
 pub struct TimeToLive(pub Timer);
+/// Keeps the entity on a wall for a moment after it starts pulling away
 #[derive(Debug, Default, Properties)]
 pub struct WallStickTimer(pub Timer);
 
+/// Set while the entity presses into a wall in the air, capping its fall speed
+#[derive(Debug, Default, Properties)]
+pub struct WallSlide(pub bool);
+
 #[derive(Debug, Default, Properties)]
 pub struct SpawnTimer(pub Timer);
 
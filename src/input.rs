@@ -0,0 +1,343 @@
+use bevy::{
+    prelude::*,
+    input::gamepad::{Gamepad, GamepadButton, GamepadButtonType},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::util;
+
+/// Something the player can do, bound to one or more inputs in the `InputMap`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputAction {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Shoot,
+    PreviousWeapon,
+    NextWeapon,
+    Weapon1,
+    Weapon2,
+    Weapon3,
+    Weapon4,
+    Start,
+    Pause,
+    Restart,
+    ChangeMode,
+    Options,
+    ToggleTrajectory,
+    WatchReplay,
+}
+
+impl InputAction {
+    /// Listed in this order on the rebinding screen
+    pub const ALL: [InputAction; 19] = [
+        InputAction::MoveUp,
+        InputAction::MoveDown,
+        InputAction::MoveLeft,
+        InputAction::MoveRight,
+        InputAction::Jump,
+        InputAction::Shoot,
+        InputAction::PreviousWeapon,
+        InputAction::NextWeapon,
+        InputAction::Weapon1,
+        InputAction::Weapon2,
+        InputAction::Weapon3,
+        InputAction::Weapon4,
+        InputAction::Start,
+        InputAction::Pause,
+        InputAction::Restart,
+        InputAction::ChangeMode,
+        InputAction::Options,
+        InputAction::ToggleTrajectory,
+        InputAction::WatchReplay,
+    ];
+
+    pub const WEAPONS: [InputAction; 4] = [
+        InputAction::Weapon1,
+        InputAction::Weapon2,
+        InputAction::Weapon3,
+        InputAction::Weapon4,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputAction::MoveUp => "Move up",
+            InputAction::MoveDown => "Move down",
+            InputAction::MoveLeft => "Move left",
+            InputAction::MoveRight => "Move right",
+            InputAction::Jump => "Jump",
+            InputAction::Shoot => "Shoot",
+            InputAction::PreviousWeapon => "Previous weapon",
+            InputAction::NextWeapon => "Next weapon",
+            InputAction::Weapon1 => "Water gun",
+            InputAction::Weapon2 => "Hose",
+            InputAction::Weapon3 => "Bucket",
+            InputAction::Weapon4 => "Ice shot",
+            InputAction::Start => "Start",
+            InputAction::Pause => "Pause",
+            InputAction::Restart => "Restart",
+            InputAction::ChangeMode => "Change mode",
+            InputAction::Options => "Options",
+            InputAction::ToggleTrajectory => "Toggle trajectory",
+            InputAction::WatchReplay => "Watch replay",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// Button on the gamepad of whichever player reads the action
+    Gamepad(GamepadButtonType),
+}
+
+impl InputBinding {
+    pub fn name(&self) -> String {
+        match self {
+            InputBinding::Key(key) => format!("{:?}", key),
+            InputBinding::Mouse(button) => format!("Mouse {:?}", button),
+            InputBinding::Gamepad(button) => format!("Pad {:?}", button),
+        }
+    }
+}
+
+/// Current state of every input device, needed to check a binding
+pub struct InputSources<'a> {
+    pub keyboard: &'a Input<KeyCode>,
+    pub mouse: &'a Input<MouseButton>,
+    pub gamepad_buttons: &'a Input<GamepadButton>,
+}
+
+impl<'a> InputSources<'a> {
+    /// First input pressed this frame, used when rebinding
+    pub fn any_just_pressed(&self) -> Option<InputBinding> {
+        self.keyboard.get_just_pressed().next().map(|key| InputBinding::Key(*key))
+            .or_else(|| self.mouse.get_just_pressed().next().map(|button| InputBinding::Mouse(*button)))
+            .or_else(|| self.gamepad_buttons.get_just_pressed().next().map(|button| InputBinding::Gamepad(button.1)))
+    }
+}
+
+/// Devices an action is read from
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InputOwner {
+    pub keyboard_mouse: bool,
+    pub gamepad: Option<Gamepad>,
+    /// Listen to every gamepad, for menus
+    pub any_gamepad: bool,
+}
+
+impl InputOwner {
+    pub const ANY: InputOwner = InputOwner {
+        keyboard_mouse: true,
+        gamepad: None,
+        any_gamepad: true,
+    };
+
+    fn gamepads(&self) -> Vec<Gamepad> {
+        if self.any_gamepad {
+            (0..MAX_GAMEPADS).map(Gamepad).collect()
+        } else {
+            self.gamepad.into_iter().collect()
+        }
+    }
+}
+
+/// Gamepads checked when any of them may trigger an action
+const MAX_GAMEPADS: usize = 4;
+
+/// Inputs bound to each action, persisted as RON in the user's data directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputMap {
+    pub bindings: HashMap<InputAction, Vec<InputBinding>>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use InputBinding::{Key, Mouse, Gamepad as Pad};
+
+        // Pad Start both starts a run from the menus and pauses it, and Select
+        // opens the options from the menu and restarts a paused run. Each pair
+        // is read in different game states, so they never clash
+        let bindings = vec![
+            (InputAction::MoveUp, vec![Key(KeyCode::W), Key(KeyCode::Up), Pad(GamepadButtonType::DPadUp)]),
+            (InputAction::MoveDown, vec![Key(KeyCode::S), Key(KeyCode::Down), Pad(GamepadButtonType::DPadDown)]),
+            (InputAction::MoveLeft, vec![Key(KeyCode::A), Key(KeyCode::Left), Pad(GamepadButtonType::DPadLeft)]),
+            (InputAction::MoveRight, vec![Key(KeyCode::D), Key(KeyCode::Right), Pad(GamepadButtonType::DPadRight)]),
+            (InputAction::Jump, vec![Key(KeyCode::Space), Pad(GamepadButtonType::South)]),
+            (InputAction::Shoot, vec![Mouse(MouseButton::Left), Pad(GamepadButtonType::RightTrigger2)]),
+            (InputAction::PreviousWeapon, vec![Key(KeyCode::Q), Pad(GamepadButtonType::LeftTrigger)]),
+            (InputAction::NextWeapon, vec![Key(KeyCode::E), Pad(GamepadButtonType::RightTrigger)]),
+            (InputAction::Weapon1, vec![Key(KeyCode::Key1)]),
+            (InputAction::Weapon2, vec![Key(KeyCode::Key2)]),
+            (InputAction::Weapon3, vec![Key(KeyCode::Key3)]),
+            (InputAction::Weapon4, vec![Key(KeyCode::Key4)]),
+            (InputAction::Start, vec![Key(KeyCode::Return), Pad(GamepadButtonType::Start)]),
+            (InputAction::Pause, vec![Key(KeyCode::Escape), Pad(GamepadButtonType::Start)]),
+            (InputAction::Restart, vec![Key(KeyCode::R), Pad(GamepadButtonType::Select)]),
+            (InputAction::ChangeMode, vec![Key(KeyCode::M), Pad(GamepadButtonType::North)]),
+            (InputAction::Options, vec![Key(KeyCode::O), Pad(GamepadButtonType::Select)]),
+            (InputAction::ToggleTrajectory, vec![Key(KeyCode::T)]),
+            (InputAction::WatchReplay, vec![Key(KeyCode::P), Pad(GamepadButtonType::West)]),
+        ];
+
+        Self {
+            bindings: bindings.into_iter().collect(),
+        }
+    }
+}
+
+impl InputMap {
+    const FILE_NAME: &'static str = "input.ron";
+
+    pub fn load() -> Self {
+        util::load_data::<Self>(Self::FILE_NAME).with_missing_defaults()
+    }
+
+    /// Actions missing from the map, as when new ones were added, get their default bindings
+    fn with_missing_defaults(mut self) -> Self {
+        for (action, bindings) in Self::default().bindings {
+            self.bindings.entry(action).or_insert(bindings);
+        }
+
+        self
+    }
+
+    pub fn save(&self) {
+        util::save_data(Self::FILE_NAME, self);
+    }
+
+    pub fn get_bindings(&self, action: InputAction) -> &[InputBinding] {
+        self.bindings.get(&action).map(|bindings| bindings.as_slice()).unwrap_or(&[])
+    }
+
+    /// Names of the inputs bound to an action for display, `-` when unbound
+    pub fn binding_names(&self, action: InputAction) -> String {
+        let names: Vec<String> = self
+            .get_bindings(action)
+            .iter()
+            .map(|binding| binding.name())
+            .collect();
+
+        if names.is_empty() {
+            "-".to_string()
+        } else {
+            names.join(", ")
+        }
+    }
+
+    pub fn bind(&mut self, action: InputAction, binding: InputBinding) {
+        let bindings = self.bindings.entry(action).or_insert_with(Vec::new);
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Unbind every input of an action, kept as an empty list so loading doesn't restore the defaults
+    pub fn clear(&mut self, action: InputAction) {
+        self.bindings.insert(action, Vec::new());
+    }
+
+    pub fn pressed(&self, action: InputAction, sources: &InputSources, owner: InputOwner) -> bool {
+        self.is_active(action, owner, |binding| match binding {
+            InputBinding::Key(key) => sources.keyboard.pressed(key),
+            InputBinding::Mouse(button) => sources.mouse.pressed(button),
+            InputBinding::Gamepad(_) => false,
+        }, |button| sources.gamepad_buttons.pressed(button))
+    }
+
+    pub fn just_pressed(&self, action: InputAction, sources: &InputSources, owner: InputOwner) -> bool {
+        self.is_active(action, owner, |binding| match binding {
+            InputBinding::Key(key) => sources.keyboard.just_pressed(key),
+            InputBinding::Mouse(button) => sources.mouse.just_pressed(button),
+            InputBinding::Gamepad(_) => false,
+        }, |button| sources.gamepad_buttons.just_pressed(button))
+    }
+
+    fn is_active(
+        &self,
+        action: InputAction,
+        owner: InputOwner,
+        keyboard_mouse: impl Fn(InputBinding) -> bool,
+        gamepad: impl Fn(GamepadButton) -> bool,
+    ) -> bool {
+        let gamepads = owner.gamepads();
+
+        self.get_bindings(action).iter().any(|binding| match *binding {
+            InputBinding::Gamepad(button) => gamepads
+                .iter()
+                .any(|pad| gamepad(GamepadButton(*pad, button))),
+            _ => owner.keyboard_mouse && keyboard_mouse(*binding),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_actions_get_default_bindings() {
+        let mut bindings = HashMap::new();
+        bindings.insert(InputAction::Jump, vec![InputBinding::Key(KeyCode::W)]);
+
+        let input_map = InputMap { bindings }.with_missing_defaults();
+
+        assert_eq!(input_map.get_bindings(InputAction::Jump), &[InputBinding::Key(KeyCode::W)]);
+        assert_eq!(
+            input_map.get_bindings(InputAction::Shoot),
+            InputMap::default().get_bindings(InputAction::Shoot),
+        );
+        assert!(InputAction::ALL.iter().all(|action| input_map.bindings.contains_key(action)));
+    }
+
+    #[test]
+    fn cleared_actions_stay_unbound_after_saving() {
+        let mut input_map = InputMap::default();
+        input_map.clear(InputAction::Jump);
+
+        let saved = ron::ser::to_string(&input_map).unwrap();
+        let loaded = ron::de::from_str::<InputMap>(&saved).unwrap().with_missing_defaults();
+
+        assert!(loaded.get_bindings(InputAction::Jump).is_empty());
+    }
+
+    #[test]
+    fn binding_twice_keeps_one() {
+        let mut input_map = InputMap::default();
+        input_map.clear(InputAction::Jump);
+        input_map.bind(InputAction::Jump, InputBinding::Key(KeyCode::J));
+        input_map.bind(InputAction::Jump, InputBinding::Key(KeyCode::J));
+
+        assert_eq!(input_map.get_bindings(InputAction::Jump), &[InputBinding::Key(KeyCode::J)]);
+    }
+
+    #[test]
+    fn actions_are_read_from_the_owner_devices() {
+        let mut keyboard = Input::<KeyCode>::default();
+        let mouse = Input::<MouseButton>::default();
+        let mut gamepad_buttons = Input::<GamepadButton>::default();
+        keyboard.press(KeyCode::Space);
+        gamepad_buttons.press(GamepadButton(Gamepad(1), GamepadButtonType::South));
+
+        let sources = InputSources {
+            keyboard: &keyboard,
+            mouse: &mouse,
+            gamepad_buttons: &gamepad_buttons,
+        };
+        let input_map = InputMap::default();
+        let owner = |keyboard_mouse, gamepad| InputOwner {
+            keyboard_mouse,
+            gamepad,
+            any_gamepad: false,
+        };
+
+        assert!(input_map.pressed(InputAction::Jump, &sources, owner(true, None)));
+        assert!(!input_map.pressed(InputAction::Jump, &sources, owner(false, Some(Gamepad(0)))));
+        assert!(input_map.pressed(InputAction::Jump, &sources, owner(false, Some(Gamepad(1)))));
+        assert!(input_map.just_pressed(InputAction::Jump, &sources, InputOwner::ANY));
+    }
+}
@@ -8,7 +8,12 @@ use bevy::{
 };
 
 mod setup;
+mod ron_asset;
 mod animation;
+mod sprite_sheet;
+mod parallax;
+mod tuning;
+mod input;
 mod util;
 mod res;
 mod comp;
@@ -24,11 +29,13 @@ fn main() {
             resizable: false,
             ..Default::default()
         })
+        // Asset loaders registered by the game plugins need the asset server
+        .add_plugins(DefaultPlugins)
         .add_plugin(FrameTimeDiagnosticsPlugin::default())
+        .add_plugin(tuning::TuningPlugin)
         .add_plugin(animation::AnimationPlugin)
+        .add_plugin(parallax::ParallaxPlugin)
         .add_plugin(sys::GameLogicPlugin)
         .add_plugin(setup::GameSetupPlugin)
-        .add_plugins(DefaultPlugins)
-        .add_resource(comp::physics::Gravity(9.82 * 40.))
         .run();
 }
\ No newline at end of file
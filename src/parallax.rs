@@ -0,0 +1,210 @@
+use bevy::{
+    prelude::*,
+    asset::{AssetPath, LoadContext},
+    type_registry::TypeUuid,
+};
+use serde::Deserialize;
+use crate::comp::actor;
+use crate::ron_asset::{self, RonAsset, RonAssetLoader};
+use crate::util::SCR_WIDTH;
+
+/// Layers used by the default level
+pub const DEFAULT_BACKGROUND: &str = "backgrounds/house.parallax";
+
+/// Background layers loaded from a `.parallax` RON file, back to front
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "0d6e2b7c-41f9-4a85-b3e2-9c7f1a6d5e28"]
+pub struct Parallax {
+    pub layers: Vec<LayerDescriptor>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LayerDescriptor {
+    pub name: String,
+    /// Texture path relative to the assets folder, drawn tinted by `color`
+    #[serde(default)]
+    pub texture: Option<String>,
+    #[serde(default = "default_color")]
+    pub color: (f32, f32, f32, f32),
+    pub size: (f32, f32),
+    /// Height of the layer center on screen
+    pub y: f32,
+    pub z: f32,
+    /// Part of the camera movement the layer follows, 0 is fixed on screen
+    pub factor: f32,
+    /// Distance between two tiles of the layer
+    pub spacing: f32,
+}
+
+fn default_color() -> (f32, f32, f32, f32) {
+    (1., 1., 1., 1.)
+}
+
+impl LayerDescriptor {
+    /// Tiles needed to cover the screen while wrapping around
+    fn tiles_count(&self) -> usize {
+        (SCR_WIDTH / self.spacing).ceil() as usize + 2
+    }
+}
+
+impl Parallax {
+    /// Tiles are laid out `spacing` apart, anything but a positive spacing can't cover the screen
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        if let Some(layer) = self.layers.iter().find(|layer| !layer.spacing.is_finite() || layer.spacing <= 0.) {
+            anyhow::bail!("Layer {} has a spacing of {}, it must be a positive number", layer.name, layer.spacing);
+        }
+
+        Ok(())
+    }
+}
+
+impl RonAsset for Parallax {
+    const EXTENSIONS: &'static [&'static str] = &["parallax"];
+
+    fn prepare(&mut self, _load_context: &mut LoadContext) -> Result<Vec<AssetPath<'static>>, anyhow::Error> {
+        self.validate()?;
+
+        Ok(Vec::new())
+    }
+}
+
+/// Background of the current level
+pub struct ParallaxHandle(pub Handle<Parallax>);
+
+/// Tile of a background layer
+struct ParallaxTile {
+    factor: f32,
+    /// Offset from the first tile of the layer
+    offset: f32,
+    /// Length after which the tiles of the layer repeat
+    span: f32,
+    y: f32,
+}
+
+pub struct ParallaxPlugin;
+
+impl Plugin for ParallaxPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<Parallax>()
+            .init_asset_loader::<RonAssetLoader<Parallax>>()
+            .add_startup_system(load_parallax_system.system())
+            .add_system(spawn_parallax_system.system())
+            .add_system(parallax_system.system());
+    }
+}
+
+fn load_parallax_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    let handle: Handle<Parallax> = asset_server.load(DEFAULT_BACKGROUND);
+    commands.insert_resource(ParallaxHandle(handle));
+}
+
+/// Spawn the tiles of the level background, again when the file changes on disk
+fn spawn_parallax_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    current: Res<ParallaxHandle>,
+    mut event_reader: Local<EventReader<AssetEvent<Parallax>>>,
+    events: Res<Events<AssetEvent<Parallax>>>,
+    assets: Res<Assets<Parallax>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    tiles: Query<With<ParallaxTile, Entity>>,
+) {
+    for handle in event_reader.iter(&events).filter_map(ron_asset::loaded_handle) {
+        if *handle != current.0 {
+            continue;
+        }
+
+        let parallax = match assets.get(handle) {
+            Some(parallax) => parallax,
+            None => continue,
+        };
+
+        for entity in &mut tiles.iter() {
+            commands.despawn(entity);
+        }
+
+        for layer in parallax.layers.iter() {
+            let (r, g, b, a) = layer.color;
+            let material = materials.add(ColorMaterial {
+                color: Color::rgba(r, g, b, a),
+                texture: layer.texture.as_ref().map(|path| asset_server.load(path.as_str())),
+            });
+
+            let count = layer.tiles_count();
+            for index in 0..count {
+                commands
+                    .spawn(SpriteComponents {
+                        material: material.clone(),
+                        transform: Transform::from_translation(Vec3::new(0., layer.y, layer.z)),
+                        sprite: Sprite {
+                            size: Vec2::new(layer.size.0, layer.size.1),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .with(ParallaxTile {
+                        factor: layer.factor,
+                        offset: index as f32 * layer.spacing,
+                        span: count as f32 * layer.spacing,
+                        y: layer.y,
+                    });
+            }
+        }
+    }
+}
+
+/// Move the tiles by their layer's part of the camera movement, wrapping
+/// them around the view
+fn parallax_system(
+    cameras: Query<With<actor::MainCamera, &Transform>>,
+    mut tiles: Query<(&ParallaxTile, &mut Transform)>,
+) {
+    for camera_transform in &mut cameras.iter() {
+        let camera = camera_transform.translation;
+
+        for (tile, mut transform) in tiles.iter_mut() {
+            let half_span = tile.span / 2.;
+            let x = (tile.offset - camera.x() * tile.factor + half_span).rem_euclid(tile.span) - half_span;
+            let y = tile.y + camera.y() * (1. - tile.factor);
+
+            transform.translation.set_x(camera.x() + x);
+            transform.translation.set_y(y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parallax_with_spacing(spacing: f32) -> Parallax {
+        Parallax {
+            layers: vec![LayerDescriptor {
+                name: "hills".to_string(),
+                texture: None,
+                color: default_color(),
+                size: (400., 200.),
+                y: 0.,
+                z: -10.,
+                factor: 0.5,
+                spacing,
+            }],
+        }
+    }
+
+    #[test]
+    fn positive_spacing_is_valid() {
+        assert!(parallax_with_spacing(400.).validate().is_ok());
+    }
+
+    #[test]
+    fn non_positive_or_non_finite_spacing_is_rejected() {
+        assert!(parallax_with_spacing(0.).validate().is_err());
+        assert!(parallax_with_spacing(-400.).validate().is_err());
+        assert!(parallax_with_spacing(f32::INFINITY).validate().is_err());
+        assert!(parallax_with_spacing(f32::NAN).validate().is_err());
+    }
+}
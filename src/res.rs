@@ -1,17 +1,197 @@
 use bevy::prelude::*;
 use bevy::sprite::collide_aabb::Collision;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use crate::comp;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::{comp, util};
 
 pub struct Colors;
 impl Colors {
     pub const WATER: Color = Color::rgb_linear(212. / 255., 241. / 255., 249. / 255.);
+    pub const ICE: Color = Color::rgb_linear(160. / 255., 220. / 255., 1.);
     pub const LAVA: Color = Color::rgb_linear(207. / 255., 16. / 255., 32. / 255.);
     pub const INTENSE_LAVA: Color = Color::rgb_linear(238. / 255., 18. / 255., 66. / 255.);
+    pub const OBSIDIAN: Color = Color::rgb_linear(40. / 255., 33. / 255., 45. / 255.);
+    pub const CRACKED_OBSIDIAN: Color = Color::rgb_linear(95. / 255., 40. / 255., 35. / 255.);
+    pub const STEAM: Color = Color::rgba_linear(235. / 255., 235. / 255., 235. / 255., 0.5);
+    pub const REFILL_STATION: Color = Color::rgba_linear(90. / 255., 170. / 255., 220. / 255., 0.5);
     pub const LINEN: Color = Color::rgba_linear(246. / 255., 242. / 255., 237. / 255., 0.6);
 }
 
+pub const FONT: &str = "fonts/DejaVuSans.ttf";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameState {
+    Menu,
+    /// Options menu opened from the main menu
+    Options,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        GameState::Menu
+    }
+}
+
+impl GameState {
+    pub fn is_playing(&self) -> bool {
+        *self == GameState::Playing
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Single screen between two walls, survive as long as possible
+    Arena,
+    /// The view scrolls right, falling behind its left edge is death
+    Runner,
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        GameMode::Arena
+    }
+}
+
+impl GameMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Arena => "Arena",
+            GameMode::Runner => "Runner",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            GameMode::Arena => GameMode::Runner,
+            GameMode::Runner => GameMode::Arena,
+        }
+    }
+}
+
+/// Scrolling of the runner mode, stays at the start in the arena
+#[derive(Debug)]
+pub struct RunnerState {
+    /// Center of the view along x
+    pub scroll_x: f32,
+    /// Pixels per second
+    pub speed: f32,
+}
+
+impl RunnerState {
+    pub const START_SPEED: f32 = 40.;
+    pub const ACCELERATION: f32 = 2.;
+    pub const MAX_SPEED: f32 = 220.;
+}
+
+impl Default for RunnerState {
+    fn default() -> Self {
+        Self {
+            scroll_x: 0.,
+            speed: Self::START_SPEED,
+        }
+    }
+}
+
+/// Hands out player ids, each spawned player gets its own
+#[derive(Debug, Default)]
+pub struct PlayerIds {
+    next: usize,
+}
+
+impl PlayerIds {
+    pub fn next(&mut self) -> usize {
+        let id = self.next;
+        self.next += 1;
+        id
+    }
+}
+
+/// Sent when a new run starts, systems owning run state should reset it
+pub struct RestartEvent;
+
+/// Random numbers for everything that plays out during a run, reseeded when
+/// a run starts so a replay of it rolls the same numbers. Effects that don't
+/// touch gameplay roll from `CosmeticRng` instead
+pub struct GameRng {
+    pub seed: u64,
+    rng: StdRng,
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(rand::random())
+    }
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        *self = Self::new(seed);
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+/// Random numbers for particles and other effects, seeded from the run seed
+/// alongside `GameRng` so a replay looks the same without the effects
+/// shifting the gameplay rolls
+#[derive(Default)]
+pub struct CosmeticRng(GameRng);
+
+impl CosmeticRng {
+    pub fn reseed(&mut self, seed: u64) {
+        // Flipped so the effects don't roll the same stream as the gameplay
+        self.0.reseed(!seed);
+    }
+}
+
+impl RngCore for CosmeticRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
 pub struct GroundCollisionEvent {
+    pub hit_entity: Entity,
     pub hit_collision: Collision, 
     pub hit_transform: Transform,
     pub hit_size: Vec2,
@@ -23,6 +203,39 @@ pub struct GroundContactListenerState {
     pub event_reader: EventReader<GroundCollisionEvent>,
 }
 
+pub struct DeathEvent {
+    pub entity: Entity,
+}
+
+/// Sent on the frame a player goes from airborne to standing on `hit_entity`
+pub struct LandingEvent {
+    pub entity: Entity,
+    pub hit_entity: Entity,
+}
+
+#[derive(Default)]
+pub struct DeathListenerState {
+    pub event_reader: EventReader<DeathEvent>,
+}
+
+/// Sent when an animation reaches a frame with an event declared on it
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub name: String,
+}
+
+pub struct ProjectileHitEvent {
+    pub projectile: Entity,
+    pub projectile_kind: comp::actor::WeaponKind,
+    pub hit_entity: Entity,
+    pub contact_point: Vec2,
+}
+
+/// Add trauma to the camera shake, from 0 to 1
+pub struct CameraShakeEvent {
+    pub trauma: f32,
+}
+
 pub struct ShootEvent;
 
 #[derive(Default)]
@@ -45,4 +258,239 @@ pub struct WallJumpListenerState(pub EventReader<WallJumpEvent>);
 #[derive(Debug, Default)]
 pub struct ColorMaterialStorage {
     pub storage: HashMap<String, Handle<ColorMaterial>>,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Default)]
+pub struct Score {
+    pub mode: GameMode,
+    pub time: f32,
+    pub furniture_landings: u32,
+    pub wall_jumps: u32,
+    /// Pixels scrolled in the runner mode
+    pub distance: f32,
+}
+
+impl Score {
+    const POINTS_PER_SECOND: f32 = 10.;
+    const POINTS_PER_LANDING: u32 = 50;
+    const POINTS_PER_WALL_JUMP: u32 = 25;
+    pub const PIXELS_PER_METER: f32 = 32.;
+
+    pub fn total(&self) -> u32 {
+        match self.mode {
+            GameMode::Arena => {
+                (self.time * Self::POINTS_PER_SECOND) as u32
+                    + self.furniture_landings * Self::POINTS_PER_LANDING
+                    + self.wall_jumps * Self::POINTS_PER_WALL_JUMP
+            },
+            GameMode::Runner => self.get_meters() as u32,
+        }
+    }
+
+    pub fn get_meters(&self) -> f32 {
+        self.distance / Self::PIXELS_PER_METER
+    }
+
+    pub fn reset(&mut self, mode: GameMode) {
+        *self = Self {
+            mode,
+            ..Self::default()
+        };
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighScoreEntry {
+    #[serde(default)]
+    pub mode: GameMode,
+    pub score: u32,
+    pub time: f32,
+    /// Seconds since unix epoch
+    pub date: u64,
+}
+
+impl HighScoreEntry {
+    pub fn new(score: &Score) -> Self {
+        Self {
+            mode: score.mode,
+            score: score.total(),
+            time: score.time,
+            date: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Top runs, persisted as RON in the user's data directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    pub entries: Vec<HighScoreEntry>,
+}
+
+impl HighScores {
+    pub const MAX_ENTRIES: usize = 10;
+    const FILE_NAME: &'static str = "highscores.ron";
+
+    pub fn load() -> Self {
+        util::load_data(Self::FILE_NAME)
+    }
+
+    pub fn save(&self) {
+        util::save_data(Self::FILE_NAME, self);
+    }
+
+    /// Insert a run, keeping the entries sorted and capped at `MAX_ENTRIES` per mode
+    pub fn insert(&mut self, entry: HighScoreEntry) {
+        self.entries.push(entry);
+        self.entries.sort_by(|a, b| b.score.cmp(&a.score));
+
+        let mut kept = HashMap::new();
+        self.entries.retain(|entry| {
+            let count = kept.entry(entry.mode).or_insert(0);
+            *count += 1;
+            *count <= Self::MAX_ENTRIES
+        });
+    }
+
+    pub fn best(&self, mode: GameMode) -> u32 {
+        self.entries
+            .iter()
+            .find(|entry| entry.mode == mode)
+            .map(|entry| entry.score)
+            .unwrap_or(0)
+    }
+}
+
+/// Player preferences, persisted as RON in the user's data directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Show the projectile arc through the crosshair
+    pub show_trajectory: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            show_trajectory: true,
+        }
+    }
+}
+
+impl Settings {
+    const FILE_NAME: &'static str = "settings.ron";
+
+    pub fn load() -> Self {
+        util::load_data(Self::FILE_NAME)
+    }
+
+    pub fn save(&self) {
+        util::save_data(Self::FILE_NAME, self);
+    }
+}
+
+/// Input of every player for one frame of a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    pub delta_seconds: f32,
+    /// Player id and the controller it reads this frame
+    pub controllers: Vec<(usize, comp::actor::Controller)>,
+}
+
+/// A recorded run, persisted as compact RON in the user's data directory
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    const FILE_NAME: &'static str = "replay.ron";
+
+    pub fn load() -> Self {
+        util::load_data(Self::FILE_NAME)
+    }
+
+    pub fn save(&self) {
+        util::save_data_compact(Self::FILE_NAME, self);
+    }
+}
+
+/// Whether the current run is being recorded or played back
+#[derive(Debug)]
+pub enum ReplayState {
+    Idle,
+    Recording(Replay),
+    Playback {
+        replay: Replay,
+        /// Next frame to feed into the controllers
+        frame: usize,
+    },
+}
+
+impl Default for ReplayState {
+    fn default() -> Self {
+        ReplayState::Idle
+    }
+}
+
+impl ReplayState {
+    pub fn is_playback(&self) -> bool {
+        matches!(self, ReplayState::Playback { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(mode: GameMode, score: u32) -> HighScoreEntry {
+        HighScoreEntry {
+            mode,
+            score,
+            time: 0.,
+            date: 0,
+        }
+    }
+
+    #[test]
+    fn high_scores_are_sorted_best_first() {
+        let mut high_scores = HighScores::default();
+        for score in [30, 10, 50, 20].iter() {
+            high_scores.insert(entry(GameMode::Arena, *score));
+        }
+
+        let scores: Vec<u32> = high_scores.entries.iter().map(|entry| entry.score).collect();
+        assert_eq!(scores, vec![50, 30, 20, 10]);
+        assert_eq!(high_scores.best(GameMode::Arena), 50);
+    }
+
+    #[test]
+    fn high_scores_are_capped_per_mode() {
+        let mut high_scores = HighScores::default();
+        for score in 0..HighScores::MAX_ENTRIES as u32 + 5 {
+            high_scores.insert(entry(GameMode::Arena, score));
+        }
+        high_scores.insert(entry(GameMode::Runner, 3));
+
+        let count = |mode| high_scores.entries.iter().filter(|entry| entry.mode == mode).count();
+        assert_eq!(count(GameMode::Arena), HighScores::MAX_ENTRIES);
+        assert_eq!(count(GameMode::Runner), 1);
+        // The lowest arena runs were dropped, the runner one kept despite its score
+        let lowest_arena = high_scores.entries
+            .iter()
+            .filter(|entry| entry.mode == GameMode::Arena)
+            .map(|entry| entry.score)
+            .min();
+        assert_eq!(lowest_arena, Some(5));
+        assert_eq!(high_scores.best(GameMode::Runner), 3);
+    }
+
+    #[test]
+    fn best_is_zero_without_runs() {
+        assert_eq!(HighScores::default().best(GameMode::Runner), 0);
+    }
+}
@@ -0,0 +1,61 @@
+use bevy::{
+    prelude::*,
+    asset::{Asset, AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    utils::BoxedFuture,
+};
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+
+/// Data file loaded from RON, every type has its own extension so loaders don't clash
+pub trait RonAsset: Asset + DeserializeOwned {
+    const EXTENSIONS: &'static [&'static str];
+
+    /// Validate the values and resolve the assets it depends on, returned as dependencies
+    fn prepare(&mut self, _load_context: &mut LoadContext) -> Result<Vec<AssetPath<'static>>, anyhow::Error> {
+        Ok(Vec::new())
+    }
+}
+
+pub struct RonAssetLoader<T> {
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for RonAssetLoader<T> {
+    fn default() -> Self {
+        Self {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: RonAsset> AssetLoader for RonAssetLoader<T> {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let mut asset = ron::de::from_bytes::<T>(bytes)?;
+            let dependencies = asset.prepare(load_context)?;
+
+            let loaded = dependencies
+                .into_iter()
+                .fold(LoadedAsset::new(asset), |loaded, path| loaded.with_dependency(path));
+            load_context.set_default_asset(loaded);
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        T::EXTENSIONS
+    }
+}
+
+/// Handle of an asset that was loaded or changed on disk, none when it was removed
+pub fn loaded_handle<T: Asset>(event: &AssetEvent<T>) -> Option<&Handle<T>> {
+    match event {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle),
+        AssetEvent::Removed { .. } => None,
+    }
+}
@@ -1,15 +1,17 @@
 use crate::bevy::prelude::*;
-use crate::util::{SCR_WIDTH, SCR_HEIGHT};
-use crate::{res, comp, animation::{self, Lava, LavaAnimData}};
+use crate::util::{self, SCR_WIDTH, SCR_HEIGHT};
+use crate::{res, comp};
+use crate::animation::{Lava, LavaAnimData};
+use crate::sprite_sheet::SpriteSheet;
+use crate::tuning::Tuning;
 
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 pub struct GameSetupPlugin;
 
 impl Plugin for GameSetupPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.init_resource::<res::ColorMaterialStorage>()
-            .add_plugin(animation::AnimationPlugin)
             .add_startup_system(setup_resource.system())
             .add_startup_system(setup_player_system.system())
             .add_startup_system(setup_game_system.system())
@@ -26,15 +28,42 @@ fn setup_resource(
         materials.add(res::Colors::WATER.into())
     );
 
+    material_storage.storage.insert(
+        "Ice".to_string(), 
+        materials.add(res::Colors::ICE.into())
+    );
+
     material_storage.storage.insert(
         "Default_Furniture".to_string(), 
         materials.add(Color::rgb(0.1, 0.1, 0.1).into())
     );
 
+    for shape in comp::actor::FurnitureShape::ALL.iter() {
+        material_storage.storage.insert(
+            shape.material_name().to_string(),
+            materials.add(shape.color().into())
+        );
+    }
+
     material_storage.storage.insert(
         "Dust".to_string(), 
         materials.add(res::Colors::LINEN.into())
     );
+
+    material_storage.storage.insert(
+        "Obsidian".to_string(), 
+        materials.add(res::Colors::OBSIDIAN.into())
+    );
+
+    material_storage.storage.insert(
+        "Obsidian_Cracked".to_string(), 
+        materials.add(res::Colors::CRACKED_OBSIDIAN.into())
+    );
+
+    material_storage.storage.insert(
+        "Steam".to_string(), 
+        materials.add(res::Colors::STEAM.into())
+    );
 }
 
 fn setup_game_system(
@@ -42,7 +71,14 @@ fn setup_game_system(
     asset_server: Res<AssetServer>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
-    commands.spawn(Camera2dComponents::default());
+    commands
+        .spawn(Camera2dComponents::default())
+        .with(comp::actor::MainCamera)
+        .with(comp::actor::CameraFollow {
+            bounds: util::arena_bounds(),
+            ..Default::default()
+        })
+        .with(comp::actor::CameraShake::default());
 
     let texture_handle = asset_server.load("lava_floor.png");
 
@@ -61,7 +97,9 @@ fn setup_game_system(
             w: SCR_WIDTH,
             h: texture_size,
         })
-        .with(comp::stats::Ground);
+        .with(comp::stats::Ground)
+        .with(comp::stats::Hazard)
+        .with(comp::stats::FollowView(0.));
 
     // Walls
     commands
@@ -80,7 +118,30 @@ fn setup_game_system(
             w: 40.,
             h: SCR_HEIGHT,
         })
-        .with(comp::stats::Ground);
+        .with(comp::stats::Ground)
+        .with(comp::stats::Recyclable {
+            start_x: SCR_WIDTH / 2. - 100.,
+        });
+
+    // Refill station on top of the left wall
+    commands
+        .spawn(SpriteComponents {
+            material: materials.add(res::Colors::REFILL_STATION.into()),
+            transform: Transform::from_translation(Vec3::new(-SCR_WIDTH / 2. + 100., 20., 0.)),
+            sprite: Sprite {
+                size: Vec2::new(40., 40.),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(comp::physics::ColliderBox {
+            w: 40.,
+            h: 40.,
+        })
+        .with(comp::stats::RefillStation(40.))
+        .with(comp::stats::Recyclable {
+            start_x: -SCR_WIDTH / 2. + 100.,
+        });
 
     commands
         .spawn(SpriteComponents {
@@ -98,24 +159,28 @@ fn setup_game_system(
             w: 40.,
             h: SCR_HEIGHT,
         })
-        .with(comp::stats::Wall);
+        .with(comp::stats::Wall)
+        .with(comp::stats::Recyclable {
+            start_x: -SCR_WIDTH / 2. + 100.,
+        });
 }
 
 fn setup_player_system(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    tuning: Res<Tuning>,
+    mut player_ids: ResMut<res::PlayerIds>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
 ) {
-    let texture_handle = asset_server.load("player_animation.png");
-    let texture_size = Vec2::new(42., 42.); // Vec2::new(320., 96.);
-    let texture_atlas = TextureAtlas::from_grid(texture_handle, texture_size, 10, 3);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
+    // Texture atlas and animations are added once the sheet is loaded
+    let sprite_sheet: Handle<SpriteSheet> = asset_server.load("player_animation.sheet");
+
+    // On top of the right wall, out of reach from the lava
+    let start_position = Vec2::new(SCR_WIDTH / 2. - 100., 40.);
 
     commands
         .spawn(SpriteSheetComponents {
-            texture_atlas: texture_atlas_handle,
-            transform: Transform::from_translation(Vec3::new(100., -SCR_HEIGHT / 2. + 80., 0.)),
+            transform: Transform::from_translation(start_position.extend(0.)),
             draw: Draw {
                 is_transparent: true,
                 is_visible: true,
@@ -123,22 +188,26 @@ fn setup_player_system(
             },
             ..Default::default()
         })
-        .with(Timer::from_seconds(0.1, true)) // Anim timer
+        .with(sprite_sheet)
         .with(comp::physics::Velocity::default())
-        .with(comp::physics::Drag(1.85))
-        .with(comp::actor::Player::default())
+        .with(comp::physics::Drag(tuning.drag))
+        .with(comp::actor::Player::new(player_ids.next()))
+        .with(comp::stats::StartPosition(start_position))
         .with(comp::actor::Controller::default())
         .with(comp::stats::MovementSpeed {
-            accel: 100.0,
-            max: 200.0,
+            accel: tuning.movement_accel,
+            max: tuning.movement_max,
         })
-        .with(comp::stats::JumpForce(200.))
+        .with(comp::stats::JumpForce(tuning.jump_force))
         .with(comp::physics::ColliderBox {
             w: 16.,
             h: 32.,
         })
         .with(comp::physics::CollisionData::default())
         .with(comp::stats::Grounded(false))
+        .with(comp::stats::JumpGrace::default())
+        .with(comp::stats::WallStickTimer(Timer::from_seconds(tuning.wall_stick_time, false)))
+        .with(comp::stats::WallSlide(false))
         .with(comp::physics::GravitationalAttraction::default())
         .with(comp::physics::Raycast {
             origin: Vec2::zero(),
@@ -146,6 +215,7 @@ fn setup_player_system(
             t_min: 8., // Half player size
             t_max: 12.,
         })
+        .with(comp::actor::Weapon::default())
         .with(comp::stats::Facing(1.))
         .with(comp::stats::StretchTimer(Timer::from_seconds(0.6, false)));
 
@@ -167,28 +237,20 @@ fn setup_player_system(
 fn setup_lava_bubbles_system(
     mut commands: Commands, 
     asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut rng: ResMut<res::CosmeticRng>,
 ) {
-    
-    
+    let sprite_sheet: Handle<SpriteSheet> = asset_server.load("lava_bubbles.sheet");
     let width_8 = SCR_WIDTH / 8.;
-    let mut rng = thread_rng();
 
     for i in 0..8 {
-        let texture_handle = asset_server.load("lava_bubbles.png");
-        let texture_size = Vec2::new(32., 32.); //Vec2::new(800., 32.);
-        let texture_atlas = TextureAtlas::from_grid(texture_handle, texture_size, 25, 1);
-        let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
         let start_index = rng.gen_range(0, 24) as u32;
         let x = -SCR_WIDTH / 2. + 32. + i as f32 * width_8;
         
         let scale = Vec3::one() * 2.;
         let mut transform = Transform::from_scale(scale);
-        transform.translation = Vec3::new(x, -SCR_HEIGHT / 2. + texture_size.y() + 64., 1.);
+        transform.translation = Vec3::new(x, -SCR_HEIGHT / 2. + 96., 1.);
 
         commands.spawn(SpriteSheetComponents {
-            texture_atlas: texture_atlas_handle,
             transform,
             draw: Draw {
                 is_transparent: true,
@@ -197,11 +259,13 @@ fn setup_lava_bubbles_system(
             },
             ..Default::default()
         })
+        .with(sprite_sheet.clone())
+        .with(comp::stats::FollowView(x))
         .with(Timer::from_seconds(0.1, true))
         .with(Lava {
             data: LavaAnimData {
                 index: start_index,
-                frames_count: 25,
+                frames_count: 25, // Replaced by the sprite sheet once loaded
             }
         });
     }
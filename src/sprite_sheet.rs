@@ -0,0 +1,240 @@
+use bevy::{
+    prelude::*,
+    asset::{AssetPath, LoadContext},
+    type_registry::TypeUuid,
+};
+use serde::Deserialize;
+use crate::animation::{AnimData, Animation, Lava};
+use crate::res;
+use crate::ron_asset::{self, RonAsset, RonAssetLoader};
+
+/// Sprite sheet description loaded from a `.sheet` RON sidecar next to the texture,
+/// describing the grid and the animation clips in it
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "5b1f9d2e-3c8a-4f7e-9d61-2a4e8c0b7f13"]
+pub struct SpriteSheet {
+    /// Texture path relative to the sidecar
+    pub texture: String,
+    pub tile_size: (f32, f32),
+    pub columns: usize,
+    pub rows: usize,
+    /// The first clip is played when the sheet is applied
+    pub clips: Vec<ClipDescriptor>,
+    #[serde(skip)]
+    pub texture_handle: Handle<Texture>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClipDescriptor {
+    pub name: String,
+    /// First frame, inclusive
+    pub from: u32,
+    /// Last frame, inclusive
+    pub to: u32,
+    /// Seconds per frame, a single value applies to every frame and none
+    /// keeps the default duration
+    #[serde(default)]
+    pub durations: Vec<f32>,
+    /// Makes the clip one shot, switching to this clip when done
+    #[serde(default)]
+    pub fallback: Option<String>,
+    /// Events emitted on frames, the frame is relative to the clip
+    #[serde(default)]
+    pub events: Vec<(u32, String)>,
+}
+
+impl ClipDescriptor {
+    pub fn frames_count(&self) -> u32 {
+        self.to - self.from + 1
+    }
+
+    fn to_anim_data(&self) -> AnimData {
+        let data = match &self.fallback {
+            Some(fallback) => AnimData::one_shot(&self.name, self.from, self.frames_count(), fallback),
+            None => AnimData::new(&self.name, self.from, self.frames_count()),
+        };
+
+        self.events
+            .iter()
+            .fold(data, |data, (frame, name)| data.with_frame_event(*frame, name))
+            .with_frame_durations(self.durations.clone())
+    }
+}
+
+impl SpriteSheet {
+    /// Reject clips running backwards or past the grid, or with durations that
+    /// don't match their frames, they would build broken animations
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        let frames_count = (self.columns * self.rows) as u32;
+
+        for clip in self.clips.iter() {
+            if clip.to < clip.from {
+                anyhow::bail!("Clip {} ends at frame {} before its first frame {}", clip.name, clip.to, clip.from);
+            }
+            if clip.to >= frames_count {
+                anyhow::bail!("Clip {} ends at frame {} past the {} frames of the grid", clip.name, clip.to, frames_count);
+            }
+            if let Some((frame, name)) = clip.events.iter().find(|(frame, _)| *frame >= clip.frames_count()) {
+                anyhow::bail!("Event {} of clip {} is on frame {} past the end of the clip", name, clip.name, frame);
+            }
+            if clip.durations.len() > 1 && clip.durations.len() != clip.frames_count() as usize {
+                anyhow::bail!("Clip {} has {} durations for {} frames", clip.name, clip.durations.len(), clip.frames_count());
+            }
+            if clip.durations.iter().any(|duration| !duration.is_finite() || *duration <= 0.) {
+                anyhow::bail!("Clip {} has a frame duration that isn't positive", clip.name);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn build_texture_atlas(&self) -> TextureAtlas {
+        TextureAtlas::from_grid(
+            self.texture_handle.clone(),
+            Vec2::new(self.tile_size.0, self.tile_size.1),
+            self.columns,
+            self.rows,
+        )
+    }
+
+    pub fn build_animation(&self) -> Option<Animation> {
+        let start = self.clips.first()?;
+        let data = self.clips.iter().map(|clip| clip.to_anim_data()).collect();
+
+        Some(Animation::new(data, &start.name))
+    }
+}
+
+impl RonAsset for SpriteSheet {
+    const EXTENSIONS: &'static [&'static str] = &["sheet"];
+
+    /// Resolve the texture next to the sidecar, it is loaded along with the sheet
+    fn prepare(&mut self, load_context: &mut LoadContext) -> Result<Vec<AssetPath<'static>>, anyhow::Error> {
+        self.validate()?;
+
+        let texture_path = match load_context.path().parent() {
+            Some(parent) => parent.join(&self.texture),
+            None => self.texture.clone().into(),
+        };
+        let texture_path = AssetPath::new(texture_path, None);
+
+        self.texture_handle = load_context.get_handle(texture_path.clone());
+
+        Ok(vec![texture_path])
+    }
+}
+
+pub struct SpriteSheetPlugin;
+
+impl Plugin for SpriteSheetPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<SpriteSheet>()
+            .init_asset_loader::<RonAssetLoader<SpriteSheet>>()
+            .add_system(apply_sprite_sheet_system.system())
+            .add_system(reset_animation_system.system());
+    }
+}
+
+/// Build the texture atlas and animation clips of entities once their
+/// sprite sheet is loaded or changed on disk
+fn apply_sprite_sheet_system(
+    mut commands: Commands,
+    mut event_reader: Local<EventReader<AssetEvent<SpriteSheet>>>,
+    events: Res<Events<AssetEvent<SpriteSheet>>>,
+    sheets: Res<Assets<SpriteSheet>>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut query: Query<(
+        Entity,
+        &Handle<SpriteSheet>,
+        &mut Handle<TextureAtlas>,
+        Option<&mut Lava>,
+        Option<&mut Timer>,
+    )>,
+) {
+    for handle in event_reader.iter(&events).filter_map(ron_asset::loaded_handle) {
+        let sheet = match sheets.get(handle) {
+            Some(sheet) => sheet,
+            None => continue,
+        };
+
+        let atlas_handle = texture_atlases.add(sheet.build_texture_atlas());
+
+        for (entity, sheet_handle, mut texture_atlas, lava, timer) in query.iter_mut() {
+            if sheet_handle != handle {
+                continue;
+            }
+
+            *texture_atlas = atlas_handle.clone();
+
+            // Lava bubbles run their own animation with a random start frame
+            if let Some(mut lava) = lava {
+                if let Some(clip) = sheet.clips.first() {
+                    lava.data.frames_count = clip.frames_count();
+                    lava.data.index %= lava.data.frames_count;
+
+                    if let (Some(mut timer), Some(duration)) = (timer, clip.durations.first()) {
+                        timer.duration = *duration;
+                    }
+                }
+
+                continue;
+            }
+
+            if let Some(animation) = sheet.build_animation() {
+                commands.insert_one(entity, animation);
+            }
+        }
+    }
+}
+
+/// Start every animated entity over from the first clip of its sheet on restart
+fn reset_animation_system(
+    mut restart_event_reader: Local<EventReader<res::RestartEvent>>,
+    restart_events: Res<Events<res::RestartEvent>>,
+    sheets: Res<Assets<SpriteSheet>>,
+    mut query: Query<(&Handle<SpriteSheet>, &mut Animation)>,
+) {
+    if restart_event_reader.iter(&restart_events).next().is_none() {
+        return;
+    }
+
+    for (handle, mut animation) in query.iter_mut() {
+        if let Some(fresh) = sheets.get(handle).and_then(|sheet| sheet.build_animation()) {
+            *animation = fresh;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sheet_with_clip(clip: &str) -> SpriteSheet {
+        let sheet = format!(
+            "(texture: \"sheet.png\", tile_size: (32., 32.), columns: 4, rows: 2, clips: [{}])",
+            clip,
+        );
+        ron::de::from_str(&sheet).unwrap()
+    }
+
+    #[test]
+    fn clips_inside_the_grid_are_valid() {
+        assert!(sheet_with_clip("(name: \"run\", from: 4, to: 7, events: [(3, \"footstep\")])").validate().is_ok());
+    }
+
+    #[test]
+    fn clips_past_the_grid_are_rejected() {
+        assert!(sheet_with_clip("(name: \"run\", from: 4, to: 8)").validate().is_err());
+        assert!(sheet_with_clip("(name: \"run\", from: 5, to: 4)").validate().is_err());
+        assert!(sheet_with_clip("(name: \"run\", from: 4, to: 5, events: [(2, \"footstep\")])").validate().is_err());
+    }
+
+    #[test]
+    fn durations_must_cover_one_or_every_frame() {
+        assert!(sheet_with_clip("(name: \"run\", from: 0, to: 2)").validate().is_ok());
+        assert!(sheet_with_clip("(name: \"run\", from: 0, to: 2, durations: [0.1])").validate().is_ok());
+        assert!(sheet_with_clip("(name: \"run\", from: 0, to: 2, durations: [0.1, 0.2, 0.1])").validate().is_ok());
+        assert!(sheet_with_clip("(name: \"run\", from: 0, to: 2, durations: [0.1, 0.2])").validate().is_err());
+        assert!(sheet_with_clip("(name: \"run\", from: 0, to: 2, durations: [0.])").validate().is_err());
+    }
+}
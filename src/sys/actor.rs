@@ -1,11 +1,16 @@
 use bevy::prelude::*;
+use bevy::sprite::collide_aabb::{collide, Collision};
 use crate::comp::{actor, physics, stats};
 use crate::res;
 use crate::animation::{Animation, AnimCommonState, AnimStateDescriptor};
 use crate::util;
 use crate::sys;
+use crate::tuning::Tuning;
 
-use rand::{thread_rng, Rng};
+use rand::Rng;
+
+/// Seconds between dust puffs while wall sliding
+const WALL_DUST_INTERVAL: f32 = 0.08;
 
 pub struct GameActorPlugin;
 
@@ -14,14 +19,18 @@ impl Plugin for GameActorPlugin {
         app.add_event::<res::JumpEvent>()
             .add_event::<res::WallJumpEvent>()
             .add_event::<res::ShootEvent>()
+            .add_event::<res::ProjectileHitEvent>()
             .init_resource::<res::JumpListenerState>()
             .init_resource::<res::WallJumpListenerState>()
             .init_resource::<res::ShootListenerState>()
             .add_system(process_commands_system.system())
+            .add_system(wall_slide_system.system())
+            .add_system(reset_actor_system.system())
             .add_system_to_stage(stage::EVENT, jump_system.system())
             .add_system_to_stage(stage::EVENT, wall_jump_system.system())            
             .add_system(process_crosshair_system.system())
             .add_system(shoot_projectile_system.system())
+            .add_system(projectile_collision_system.system())
             .add_system_to_stage(stage::POST_UPDATE, clean_projectile_system.system());
             // .add_system(stretch_to_normal_system.system());
     }
@@ -30,10 +39,11 @@ impl Plugin for GameActorPlugin {
 // MARK - Systems
 
 pub fn process_commands_system(
+    state: Res<res::GameState>,
+    time: Res<Time>,
     mut jump_command_event: ResMut<Events<res::JumpEvent>>,
     mut wall_jump_command_event: ResMut<Events<res::WallJumpEvent>>,
     mut shoot_command_event: ResMut<Events<res::ShootEvent>>,
-    mut animation: ResMut<Animation>,
     mut query: Query<(
         &mut actor::Controller,    
         &mut physics::Velocity,
@@ -41,22 +51,54 @@ pub fn process_commands_system(
         &stats::MovementSpeed,
         &mut stats::Facing,
         &physics::CollisionData,
+        Option<&mut actor::Weapon>,
+        Option<&stats::Grounded>,
+        Option<&mut stats::WallStickTimer>,
+        Option<&mut stats::WallSlide>,
     )>,
 ) {
+    if !state.is_playing() {
+        return;
+    }
+
     for (
         mut controller, 
         mut velocity, 
         mut transform, 
         speed, 
         mut facing,
-        _collision_data,
+        collision_data,
+        mut weapon,
+        grounded,
+        wall_stick,
+        wall_slide,
     ) in query.iter_mut() {
-        let movement = if controller.movement.x() + controller.movement.y() != 0.0 {
+        // Keep analog movement, only diagonal keys go over a length of 1
+        let mut movement = if controller.movement.length() > 1. {
             controller.movement.normalize()
         } else {
             controller.movement
         };
 
+        let airborne = grounded.map_or(false, |grounded| !grounded.0);
+        let wall_side = if airborne { collision_data.wall_side() } else { 0. };
+
+        // Hold onto the wall for a moment before pulling away from it
+        if let Some(mut wall_stick) = wall_stick {
+            if wall_side != 0. && movement.x() * wall_side < 0. {
+                wall_stick.0.tick(time.delta_seconds);
+                if !wall_stick.0.finished {
+                    movement.set_x(0.);
+                }
+            } else {
+                wall_stick.0.reset();
+            }
+        }
+
+        if let Some(mut wall_slide) = wall_slide {
+            wall_slide.0 = wall_side != 0. && movement.x() * wall_side > 0.;
+        }
+
         let facing_direction =  clamp(movement.x(), -1., 1.);
         if facing_direction != 0. {
             facing.0 = facing_direction;
@@ -75,6 +117,17 @@ pub fn process_commands_system(
                 actor::ControllerAction::WallJump => {
                     wall_jump_command_event.send(res::WallJumpEvent);
                 },
+                actor::ControllerAction::SelectWeapon(kind) => {
+                    if let Some(weapon) = weapon.as_mut() {
+                        weapon.set_kind(kind);
+                    }
+                },
+                actor::ControllerAction::CycleWeapon(steps) => {
+                    if let Some(weapon) = weapon.as_mut() {
+                        let kind = weapon.kind.cycle(steps);
+                        weapon.set_kind(kind);
+                    }
+                },
             }
         }
 
@@ -90,21 +143,16 @@ pub fn process_commands_system(
             }
         }
 
-        if movement.x().abs() > 0. {
-            animation.set_anim(AnimCommonState::Run.name());
-        } else {
-            // TODO: - Figure out a way to detect riding on moving objects should work
-            // with updating animations
-            //if velocity.x().abs() < 8. || collision_data.either_side() {
-                animation.set_anim(AnimCommonState::Idle.name());
-            //}
-        }
-
         controller.reset_movement();
     }
 }
 
+/// Jump when on the ground or shortly after leaving it, presses made
+/// shortly before landing are buffered and fire on touchdown
 pub fn jump_system(
+    state: Res<res::GameState>,
+    time: Res<Time>,
+    tuning: Res<Tuning>,
     jump_event: Res<Events<res::JumpEvent>>,
     mut jump_event_reader: ResMut<res::JumpListenerState>,
     mut query: Query<(
@@ -113,30 +161,179 @@ pub fn jump_system(
         &stats::JumpForce,
         &mut physics::GravitationalAttraction,
         &mut stats::Grounded,
+        &mut stats::JumpGrace,
+        Option<&stats::DoubleJump>,
         &mut stats::StretchTimer,
     )>,
 ) {
-    for _event in jump_event_reader.event_reader.iter(&jump_event) {
-        for (
-            mut transform, 
-            mut velocity, 
-            jump_force, 
-            mut attraction,
-            mut grounded,
-            _stretch_timer,
-        ) in query.iter_mut() {
-            // Move the position of the player a bit up to 
-            // avoid colliding with object before jumping
-            let mut translation = transform.translation;
-            *translation.y_mut() += 2.;
-            transform.translation = translation;
+    let jump_pressed = jump_event_reader.event_reader.iter(&jump_event).count() > 0;
+    if !state.is_playing() {
+        return;
+    }
+
+    let dt = time.delta_seconds;
+
+    for (
+        mut transform, 
+        mut velocity, 
+        jump_force, 
+        mut attraction,
+        mut grounded,
+        mut grace,
+        double_jump,
+        _stretch_timer,
+    ) in query.iter_mut() {
+        if !update_jump_grace(&mut grace, grounded.0, jump_pressed, double_jump.is_some(), dt, &tuning) {
+            continue;
+        }
 
-            attraction.is_active = true;
-            grounded.0 = false;
+        // Move the position of the player a bit up to 
+        // avoid colliding with object before jumping
+        let mut translation = transform.translation;
+        *translation.y_mut() += 2.;
+        transform.translation = translation;
 
-            velocity.0.set_y(jump_force.0);
+        attraction.is_active = true;
+        grounded.0 = false;
 
-            // stretch_sprite(&mut stretch_timer, &mut transform);
+        velocity.0.set_y(jump_force.0);
+
+        // stretch_sprite(&mut stretch_timer, &mut transform);
+    }
+}
+
+/// Advance the coyote time and jump buffer by a frame, true when the entity
+/// jumps this frame
+fn update_jump_grace(
+    grace: &mut stats::JumpGrace,
+    grounded: bool,
+    jump_pressed: bool,
+    has_double_jump: bool,
+    dt: f32,
+    tuning: &Tuning,
+) -> bool {
+    if grounded {
+        grace.since_grounded = 0.;
+        grace.air_jump_used = false;
+    } else {
+        grace.since_grounded += dt;
+    }
+
+    grace.buffered = match grace.buffered {
+        _ if jump_pressed => Some(0.),
+        Some(elapsed) if elapsed + dt <= tuning.jump_buffer_time => Some(elapsed + dt),
+        _ => None,
+    };
+
+    if grace.buffered.is_none() {
+        return false;
+    }
+
+    let can_jump = grounded || grace.since_grounded <= tuning.coyote_time;
+    if !can_jump {
+        // Air jumps need a fresh press, buffered ones wait for the ground
+        if !jump_pressed || !has_double_jump || grace.air_jump_used {
+            return false;
+        }
+        grace.air_jump_used = true;
+    }
+
+    grace.buffered = None;
+    // Coyote time is used up until standing on ground again
+    grace.since_grounded = f32::INFINITY;
+
+    true
+}
+
+/// Cap the fall speed while pressing into a wall and leave a dust trail
+pub fn wall_slide_system(
+    mut commands: Commands,
+    state: Res<res::GameState>,
+    time: Res<Time>,
+    tuning: Res<Tuning>,
+    mut materials: ResMut<res::ColorMaterialStorage>,
+    mut rng: ResMut<res::CosmeticRng>,
+    mut restart_event_reader: Local<EventReader<res::RestartEvent>>,
+    restart_events: Res<Events<res::RestartEvent>>,
+    mut since_dust: Local<f32>,
+    mut query: Query<(
+        &stats::WallSlide,
+        &Transform,
+        &physics::ColliderBox,
+        &physics::CollisionData,
+        &mut physics::Velocity,
+    )>,
+) {
+    if restart_event_reader.iter(&restart_events).next().is_some() {
+        *since_dust = 0.;
+    }
+
+    if !state.is_playing() {
+        return;
+    }
+
+    *since_dust += time.delta_seconds;
+
+    for (wall_slide, transform, body, collision_data, mut velocity) in query.iter_mut() {
+        if !wall_slide.0 || velocity.y() > 0. {
+            continue;
+        }
+
+        velocity.set_y(velocity.y().max(-tuning.wall_slide_speed));
+
+        if *since_dust < WALL_DUST_INTERVAL {
+            continue;
+        }
+        *since_dust = 0.;
+
+        let wall_side = collision_data.wall_side();
+        let position = transform.translation.truncate()
+            + Vec2::new(body.w / 2. * wall_side, -body.h / 2.);
+
+        sys::particles::spawn_wall_dust_particle(
+            &mut commands,
+            &mut materials,
+            &mut rng,
+            position,
+            Vec2::new(-wall_side * 10., 20.),
+            1.,
+            2,
+        );
+    }
+}
+
+/// Face right again and forget jump, wall and stretch state from the last run
+fn reset_actor_system(
+    mut restart_event_reader: Local<EventReader<res::RestartEvent>>,
+    restart_events: Res<Events<res::RestartEvent>>,
+    mut query: Query<(
+        &mut Transform,
+        &mut stats::Facing,
+        Option<&mut stats::JumpGrace>,
+        Option<&mut stats::WallStickTimer>,
+        Option<&mut stats::WallSlide>,
+        Option<&mut stats::StretchTimer>,
+    )>,
+) {
+    if restart_event_reader.iter(&restart_events).next().is_none() {
+        return;
+    }
+
+    for (mut transform, mut facing, jump_grace, wall_stick_timer, wall_slide, stretch_timer) in query.iter_mut() {
+        facing.0 = 1.;
+        flip_sprite(&mut transform, facing.0);
+
+        if let Some(mut jump_grace) = jump_grace {
+            *jump_grace = stats::JumpGrace::default();
+        }
+        if let Some(mut wall_stick_timer) = wall_stick_timer {
+            wall_stick_timer.0.reset();
+        }
+        if let Some(mut wall_slide) = wall_slide {
+            wall_slide.0 = false;
+        }
+        if let Some(mut stretch_timer) = stretch_timer {
+            stretch_timer.0.reset();
         }
     }
 }
@@ -144,6 +341,8 @@ pub fn jump_system(
 pub fn wall_jump_system(
     mut commands: Commands,
     mut materials: ResMut<res::ColorMaterialStorage>,
+    mut rng: ResMut<res::CosmeticRng>,
+    tuning: Res<Tuning>,
     event: Res<Events<res::WallJumpEvent>>,
     mut event_reader: ResMut<res::WallJumpListenerState>,
     mut query: Query<(
@@ -157,6 +356,8 @@ pub fn wall_jump_system(
         &mut stats::StretchTimer,
     )>,
 ) {
+    let (multiplier_x, multiplier_y) = tuning.wall_jump_multiplier;
+
     for _ in event_reader.0.iter(&event) {
         for (
             mut transform, 
@@ -177,14 +378,15 @@ pub fn wall_jump_system(
 
                 attraction.is_active = true;
 
-                velocity.0.set_x(force.0 * 1.4);                
-                velocity.0.set_y(force.0 * 1.2);
+                velocity.0.set_x(force.0 * multiplier_x);                
+                velocity.0.set_y(force.0 * multiplier_y);
                 
                 *position.x_mut() -= body.get_size().x() / 2.; 
 
                 sys::particles::spawn_wall_dust_particle(
                     &mut commands, 
                     &mut materials, 
+                    &mut rng,
                     position, 
                     Vec2::new(1., 1.),  
                     facing.0,
@@ -201,14 +403,15 @@ pub fn wall_jump_system(
 
                 attraction.is_active = true;
 
-                velocity.0.set_x(-force.0 * 1.4);                
-                velocity.0.set_y(force.0 * 1.2);
+                velocity.0.set_x(-force.0 * multiplier_x);                
+                velocity.0.set_y(force.0 * multiplier_y);
 
                 *position.x_mut() += body.get_size().x() / 2.; 
 
                 sys::particles::spawn_wall_dust_particle(
                     &mut commands, 
                     &mut materials, 
+                    &mut rng,
                     position, 
                     Vec2::new(-1., 1.),  
                     facing.0,
@@ -250,36 +453,58 @@ pub fn wall_jump_system(
 pub fn shoot_projectile_system(
     mut commands: Commands,
     materials: ResMut<res::ColorMaterialStorage>,
+    mut rng: ResMut<res::GameRng>,
     shoot_event: Res<Events<res::ShootEvent>>,
     mut shoot_event_reader: ResMut<res::ShootListenerState>,
     query_1: Query<With<actor::Crosshair, &Transform>>,
-    query_2: Query<With<actor::Player, &Transform>>
+    mut query_2: Query<(With<actor::Player, &Transform>, &mut actor::Weapon, Option<&mut Animation>)>
 ) {
     for _event in shoot_event_reader.event_reader.iter(&shoot_event) {
         for transform in &mut query_1.iter() {
-            for other_transform in &mut query_2.iter() {
-                let direction = util::get_direction(&other_transform.translation.truncate(), &transform.translation.truncate());
-                let projectile_velocity = direction.normalize() * 200.; // TODO - remove magic value
-
-                let upper = 20.;
-                let lower = -20.;
-                let mut rng = thread_rng();
-                let x = rng.gen_range(lower, upper);
-                let y = rng.gen_range(lower, upper);
+            for (other_transform, mut weapon, animation) in query_2.iter_mut() {
+                if !weapon.can_fire() {
+                    continue;
+                }
+                weapon.fire();
+
+                // Only shots that go off play the clip, not presses during the cooldown
+                if let Some(mut animation) = animation {
+                    if !animation.is_playing_once() {
+                        animation.set_anim(AnimCommonState::Shoot.name());
+                    }
+                }
 
-                let handle = materials.storage.get(&"Projectile".to_string()).unwrap();
+                let direction = util::get_direction(&other_transform.translation.truncate(), &transform.translation.truncate());
+                let projectile_velocity = direction.normalize() * weapon.muzzle_speed;
+
+                let (x, y) = if weapon.spread > 0. {
+                    let upper = weapon.spread;
+                    let lower = -weapon.spread;
+                    (rng.gen_range(lower, upper), rng.gen_range(lower, upper))
+                } else {
+                    (0., 0.)
+                };
+
+                let kind = weapon.kind;
+                let size = kind.projectile_size();
+                let handle = materials.storage.get(kind.material_name()).unwrap();
                 commands
                     .spawn(SpriteComponents {
                         material: handle.clone(),
                         transform: Transform::from_translation(transform.translation.clone()),
                         sprite: Sprite {
-                            size: Vec2::new(5., 5.),
+                            size: Vec2::new(size, size),
                             ..Default::default()
                         },
                         ..Default::default()
                     })
                     .with(actor::Projectile {
                         direction,
+                        kind,
+                    })
+                    .with(physics::ColliderBox {
+                        w: size,
+                        h: size,
                     })
                     .with(stats::TimeToLive(Timer::from_seconds(2.0, true)))
                     .with(physics::GravitationalAttraction::default())
@@ -292,39 +517,105 @@ pub fn shoot_projectile_system(
     }
 }
 
+/// Splash projectiles against walls and ground, including furniture and lava
+pub fn projectile_collision_system(
+    mut commands: Commands,
+    state: Res<res::GameState>,
+    mut materials: ResMut<res::ColorMaterialStorage>,
+    mut rng: ResMut<res::CosmeticRng>,
+    mut hit_events: ResMut<Events<res::ProjectileHitEvent>>,
+    projectiles: Query<(
+        Entity,
+        &actor::Projectile,
+        &Transform,
+        &physics::ColliderBox,
+        &physics::Velocity,
+    )>,
+    // Walls are always ground as well
+    colliders: Query<(Entity, With<stats::Ground, &Transform>, &physics::ColliderBox)>,
+) {
+    if !state.is_playing() {
+        return;
+    }
+
+    for (entity, projectile, transform, body, velocity) in &mut projectiles.iter() {
+        for (other_entity, other_transform, other_body) in &mut colliders.iter() {
+            let collision = collide(
+                transform.translation,
+                body.get_size(),
+                other_transform.translation,
+                other_body.get_size(),
+            );
+
+            if let Some(collision) = collision {
+                let contact_point = get_contact_point(
+                    transform.translation.truncate(),
+                    other_transform.translation.truncate(),
+                    other_body.get_size(),
+                    &collision,
+                );
+
+                hit_events.send(res::ProjectileHitEvent {
+                    projectile: entity,
+                    projectile_kind: projectile.kind,
+                    hit_entity: other_entity,
+                    contact_point,
+                });
+
+                sys::particles::spawn_splash_particle(
+                    &mut commands,
+                    &mut materials,
+                    &mut rng,
+                    contact_point,
+                    -velocity.0.normalize(),
+                    projectile.kind.splash_particle_count(),
+                );
+
+                commands.despawn(entity);
+                break;
+            }
+        }
+    }
+}
+
 /// Shrink sprite over time then despawn it
 /// In case of out of bounds it will get despawned early
 pub fn clean_projectile_system(
     mut commands: Commands,
+    state: Res<res::GameState>,
     time: Res<Time>,
     windows: Res<Windows>,
     mut query: Query<(
         Entity, 
-        With<actor::Projectile, &mut stats::TimeToLive>, 
+        &actor::Projectile,
+        &mut stats::TimeToLive, 
         &mut Sprite, 
         &Transform
     )>,
 ) {
+    if !state.is_playing() {
+        return;
+    }
+
     let size = util::get_window_size(windows);
-    for (entity, mut timer, mut sprite, transform) in query.iter_mut() {
+    for (entity, projectile, mut timer, mut sprite, transform) in query.iter_mut() {
         // Check if still within bounds
         if transform.translation.y() < -size.height {
-            println!("Despawn projectile, out of bounds");
             commands.despawn(entity);
             
-            return;
+            continue;
         }
 
         timer.0.tick(time.delta_seconds);
         if !timer.0.finished {
             let procentage =
                 1. - (timer.0.elapsed) / timer.0.duration;
-            sprite.size = Vec2::new(5.0, 5.0) * procentage;
+            let size = projectile.kind.projectile_size();
+            sprite.size = Vec2::new(size, size) * procentage;
 
-            return;
+            continue;
         }
 
-        println!("Despawn projectile, after time");
         commands.remove_one::<Draw>(entity);
         commands.despawn(entity);
     }
@@ -336,9 +627,15 @@ pub fn process_crosshair_system(
 ) {
     for (_, controller, player_transform) in &mut query_1.iter() {
         for (crosshair, mut crosshair_transform) in query_2.iter_mut() {            
+            let player_position = player_transform.translation.truncate();
+            let target = match controller.aim_direction {
+                Some(direction) => player_position + direction,
+                None => controller.cursor_position,
+            };
+
             set_aim(
-                &player_transform.translation.truncate(),
-                &controller.cursor_position,
+                &player_position,
+                &target,
                 crosshair.distance,
                 &mut crosshair_transform,
             );
@@ -375,10 +672,81 @@ fn clamp(value: f32, min: f32, max: f32) -> f32 {
     }
 }
 
+/// Point on the edge of the box that was hit
+fn get_contact_point(position: Vec2, box_position: Vec2, box_size: Vec2, collision: &Collision) -> Vec2 {
+    let min = box_position - box_size / 2.;
+    let max = box_position + box_size / 2.;
+    let mut contact = position.max(min).min(max);
+
+    match collision {
+        Collision::Left => contact.set_x(min.x()),
+        Collision::Right => contact.set_x(max.x()),
+        Collision::Top => contact.set_y(max.y()),
+        Collision::Bottom => contact.set_y(min.y()),
+    }
+
+    contact
+}
+
 fn set_aim(a: &Vec2, b: &Vec2, distance: f32, transform: &mut Transform) {
     let direction = util::get_direction(a, b);
     let norm = direction.normalize() * distance;
     let aim = Vec2::new(a.x() + norm.x(), a.y() + norm.y()).extend(0.);
 
     transform.translation = aim;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Grace state of an entity that stood on the ground last frame
+    fn left_ground(tuning: &Tuning) -> stats::JumpGrace {
+        let mut grace = stats::JumpGrace::default();
+        assert!(!update_jump_grace(&mut grace, true, false, false, 0.016, tuning));
+        grace
+    }
+
+    #[test]
+    fn jump_inside_the_coyote_window() {
+        let tuning = Tuning::default();
+        let mut grace = left_ground(&tuning);
+
+        assert!(!update_jump_grace(&mut grace, false, false, false, tuning.coyote_time / 2., &tuning));
+        assert!(update_jump_grace(&mut grace, false, true, false, tuning.coyote_time / 4., &tuning));
+    }
+
+    #[test]
+    fn no_jump_outside_the_coyote_window() {
+        let tuning = Tuning::default();
+        let mut grace = left_ground(&tuning);
+
+        assert!(!update_jump_grace(&mut grace, false, false, false, tuning.coyote_time / 2., &tuning));
+        assert!(!update_jump_grace(&mut grace, false, true, false, tuning.coyote_time, &tuning));
+    }
+
+    #[test]
+    fn buffered_jump_fires_on_landing_in_time() {
+        let tuning = Tuning::default();
+        let mut grace = left_ground(&tuning);
+        // Falling for long past the coyote window
+        grace.since_grounded = f32::INFINITY;
+
+        assert!(!update_jump_grace(&mut grace, false, true, false, 0.016, &tuning));
+        assert!(!update_jump_grace(&mut grace, false, false, false, tuning.jump_buffer_time / 2., &tuning));
+        assert!(update_jump_grace(&mut grace, true, false, false, tuning.jump_buffer_time / 4., &tuning));
+    }
+
+    #[test]
+    fn buffered_jump_expires_before_landing() {
+        let tuning = Tuning::default();
+        let mut grace = left_ground(&tuning);
+        // Falling for long past the coyote window
+        grace.since_grounded = f32::INFINITY;
+
+        assert!(!update_jump_grace(&mut grace, false, true, false, 0.016, &tuning));
+        assert!(!update_jump_grace(&mut grace, false, false, false, tuning.jump_buffer_time / 2., &tuning));
+        assert!(!update_jump_grace(&mut grace, true, false, false, tuning.jump_buffer_time, &tuning));
+        assert_eq!(grace.buffered, None);
+    }
+}
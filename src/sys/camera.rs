@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+
+use crate::comp::{actor, physics};
+use crate::res;
+use crate::util;
+
+use rand::{thread_rng, Rng};
+
+/// Fall speed above which landing shakes the camera
+const HARD_LANDING_SPEED: f32 = 250.;
+/// Fall speed giving the most trauma a landing can add
+const MAX_LANDING_SPEED: f32 = 600.;
+const MAX_LANDING_TRAUMA: f32 = 0.5;
+/// Speed between the player and a furniture piece above which bumping into it shakes the camera
+const FURNITURE_HIT_SPEED: f32 = 200.;
+/// Speed giving the most trauma a furniture hit can add
+const MAX_FURNITURE_HIT_SPEED: f32 = 500.;
+const MAX_FURNITURE_TRAUMA: f32 = 0.4;
+const DEATH_TRAUMA: f32 = 0.8;
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<res::CameraShakeEvent>()
+            .add_system(hard_landing_shake_system.system())
+            .add_system(furniture_hit_shake_system.system())
+            .add_system(death_shake_system.system())
+            .add_system(camera_follow_system.system())
+            .add_system(camera_shake_system.system());
+    }
+}
+
+/// Move the followed position so the player stays inside the dead-zone,
+/// easing towards it and keeping the view inside the level bounds. The arena
+/// is exactly one view in size, so the view only moves in runner mode
+fn camera_follow_system(
+    state: Res<res::GameState>,
+    time: Res<Time>,
+    windows: Res<Windows>,
+    players: Query<With<actor::Player, &Transform>>,
+    mut cameras: Query<(&mut actor::CameraFollow, &Transform)>,
+) {
+    if !state.is_playing() {
+        return;
+    }
+
+    let window_size = util::get_window_size(windows);
+
+    for player_transform in &mut players.iter() {
+        let target = player_transform.translation.truncate();
+
+        for (mut follow, camera_transform) in cameras.iter_mut() {
+            let offset = target - follow.position;
+            let mut goal = follow.position;
+
+            if offset.x().abs() > follow.dead_zone.x() {
+                *goal.x_mut() += offset.x() - follow.dead_zone.x() * offset.x().signum();
+            }
+            if offset.y().abs() > follow.dead_zone.y() {
+                *goal.y_mut() += offset.y() - follow.dead_zone.y() * offset.y().signum();
+            }
+
+            let t = (follow.smoothing * time.delta_seconds).min(1.);
+            let mut position = follow.position.lerp(goal, t);
+
+            // Half of the view in world units, zooming scales the camera transform
+            let half_view = Vec2::new(window_size.width, window_size.height) / 2.
+                * camera_transform.scale.truncate();
+            let (min, max) = follow.bounds;
+            position.set_x(clamp_axis(position.x(), min.x() + half_view.x(), max.x() - half_view.x()));
+            position.set_y(clamp_axis(position.y(), min.y() + half_view.y(), max.y() - half_view.y()));
+
+            if let Some(x) = follow.lock_x {
+                position.set_x(x);
+            }
+
+            follow.position = position;
+        }
+    }
+}
+
+/// Place the camera at the followed position, offset and tilted by the shake
+fn camera_shake_system(
+    time: Res<Time>,
+    mut shake_event_reader: Local<EventReader<res::CameraShakeEvent>>,
+    shake_events: Res<Events<res::CameraShakeEvent>>,
+    mut query: Query<(&actor::CameraFollow, &mut actor::CameraShake, &mut Transform)>,
+) {
+    let events: Vec<f32> = shake_event_reader.iter(&shake_events).map(|event| event.trauma).collect();
+    // Shaking keeps going while paused, so stay off the seeded game rng
+    let mut rng = thread_rng();
+
+    for (follow, mut shake, mut transform) in query.iter_mut() {
+        for trauma in events.iter() {
+            shake.add_trauma(*trauma);
+        }
+
+        shake.trauma = (shake.trauma - shake.decay * time.delta_seconds).max(0.);
+
+        let intensity = shake.get_intensity();
+        let offset = Vec2::new(rng.gen_range(-1., 1.), rng.gen_range(-1., 1.)) * shake.max_offset * intensity;
+        let angle = rng.gen_range(-1., 1.) * shake.max_angle * intensity;
+
+        let position = follow.position + offset;
+        transform.translation.set_x(position.x());
+        transform.translation.set_y(position.y());
+        transform.rotation = Quat::from_rotation_z(angle);
+    }
+}
+
+/// Shake the camera when the player lands after a fast fall
+fn hard_landing_shake_system(
+    mut collision_event_reader: Local<EventReader<res::GroundCollisionEvent>>,
+    collision_events: Res<Events<res::GroundCollisionEvent>>,
+    mut last_fall_speed: Local<f32>,
+    mut shake_events: ResMut<Events<res::CameraShakeEvent>>,
+    players: Query<With<actor::Player, &physics::Velocity>>,
+) {
+    let landed = collision_event_reader.iter(&collision_events).next().is_some();
+
+    if landed && *last_fall_speed > HARD_LANDING_SPEED {
+        let strength = (*last_fall_speed - HARD_LANDING_SPEED) / (MAX_LANDING_SPEED - HARD_LANDING_SPEED);
+        shake_events.send(res::CameraShakeEvent {
+            trauma: strength.min(1.) * MAX_LANDING_TRAUMA,
+        });
+    }
+
+    for velocity in &mut players.iter() {
+        *last_fall_speed = -velocity.y();
+    }
+}
+
+/// Shake the camera when the player bumps into a furniture piece fast enough
+fn furniture_hit_shake_system(
+    mut collision_event_reader: Local<EventReader<res::GroundCollisionEvent>>,
+    collision_events: Res<Events<res::GroundCollisionEvent>>,
+    mut touching: Local<Vec<Entity>>,
+    mut last_player_velocity: Local<Vec2>,
+    mut shake_events: ResMut<Events<res::CameraShakeEvent>>,
+    furnitures: Query<&actor::Furniture>,
+    players: Query<With<actor::Player, &physics::Velocity>>,
+) {
+    let mut touched = Vec::new();
+
+    for event in collision_event_reader.iter(&collision_events) {
+        if furnitures.get(event.hit_entity).is_err() || touched.contains(&event.hit_entity) {
+            continue;
+        }
+        touched.push(event.hit_entity);
+
+        // Contacts are reported every frame, only the first one is a hit
+        if touching.contains(&event.hit_entity) {
+            continue;
+        }
+
+        let speed = (event.hit_velocity.0 - *last_player_velocity).length();
+        if speed > FURNITURE_HIT_SPEED {
+            let strength = (speed - FURNITURE_HIT_SPEED) / (MAX_FURNITURE_HIT_SPEED - FURNITURE_HIT_SPEED);
+            shake_events.send(res::CameraShakeEvent {
+                trauma: strength.min(1.) * MAX_FURNITURE_TRAUMA,
+            });
+        }
+    }
+
+    *touching = touched;
+
+    for velocity in &mut players.iter() {
+        *last_player_velocity = velocity.0;
+    }
+}
+
+/// Falling into the lava shakes the camera
+fn death_shake_system(
+    mut death_event_reader: Local<EventReader<res::DeathEvent>>,
+    death_events: Res<Events<res::DeathEvent>>,
+    mut shake_events: ResMut<Events<res::CameraShakeEvent>>,
+) {
+    if death_event_reader.iter(&death_events).next().is_some() {
+        shake_events.send(res::CameraShakeEvent { trauma: DEATH_TRAUMA });
+    }
+}
+
+/// Clamp that centers the value when the range is empty, as when the level
+/// is smaller than the view
+fn clamp_axis(value: f32, min: f32, max: f32) -> f32 {
+    if min > max {
+        return (min + max) / 2.;
+    }
+
+    value.max(min).min(max)
+}
@@ -2,32 +2,93 @@ use bevy::{
     prelude::*,
 };
 
-use crate::comp;
+use crate::comp::{self, actor::FurnitureShape};
 use crate::res;
-use crate::util;
+use crate::util::{SCR_WIDTH, SCR_HEIGHT};
 
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
-struct Wave;
+const TOPPLE_SPEED: f32 = 120.;
 
-struct FurnitureSpawner {
-    start_position: Vec3,
+/// Hidden furniture is parked here, out of the player's reach
+fn pool_position() -> Vec3 {
+    Vec3::new(0., -SCR_HEIGHT * 2., 0.)
+}
+
+/// Furniture from the pool, `end_position` is where the spawner recycles it
+struct Wave {
     end_position: Vec3,
-    t_min: f32,
-    t_max: f32,
-    timer: Timer,
+}
+
+/// A lane that furniture drifts along, from `start_position` to `end_position`.
+/// The y of the positions is where the bottom of the furniture is placed.
+pub struct FurnitureSpawner {
+    pub start_position: Vec3,
+    pub end_position: Vec3,
+    pub speed: f32,
+    /// Min and max seconds between spawns
+    pub t_min: f32,
+    pub t_max: f32,
+    pub timer: Timer,
+}
+
+impl FurnitureSpawner {
+    pub fn new(start_position: Vec3, end_position: Vec3, speed: f32, t_min: f32, t_max: f32) -> Self {
+        Self {
+            start_position,
+            end_position,
+            speed,
+            t_min,
+            t_max,
+            // Spawn the first furniture right away
+            timer: Timer::from_seconds(0.1, true),
+        }
+    }
+
+    /// Start and end of the lane. Outside the arena the view scrolls right and
+    /// anything starting left of it is already behind, so lanes run right to left
+    fn get_lane(&self, mode: res::GameMode) -> (Vec3, Vec3) {
+        if mode != res::GameMode::Arena && self.start_position.x() < self.end_position.x() {
+            (self.end_position, self.start_position)
+        } else {
+            (self.start_position, self.end_position)
+        }
+    }
+
+    fn get_velocity(&self, start_position: Vec3, end_position: Vec3) -> Vec2 {
+        (end_position - start_position).truncate().normalize() * self.speed
+    }
+}
+
+/// Lanes of the default level
+fn default_lanes() -> Vec<FurnitureSpawner> {
+    let left = -SCR_WIDTH / 2. - 200.;
+    let right = SCR_WIDTH / 2. + 200.;
+    let low = -SCR_HEIGHT / 2. - 18.;
+    let high = -SCR_HEIGHT / 2. + 30.;
+
+    vec![
+        FurnitureSpawner::new(Vec3::new(right, low, 0.), Vec3::new(left, low, 0.), 60., 2.9, 4.2),
+        FurnitureSpawner::new(Vec3::new(left, high, 0.), Vec3::new(right, high, 0.), 45., 4.5, 7.),
+    ]
 }
 
 pub struct FurniturePlugin;
 
 impl Plugin for FurniturePlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_resource(comp::stats::SpawnTimer(
-                Timer::from_seconds(0.1, true)
-            ))
+        app.add_startup_system(setup_spawners.system())
             .add_startup_system_to_stage("post_startup", setup_furnitures.system())
             .add_system(spawn_system.system())
-            .add_system(despawn_system.system());
+            .add_system(despawn_system.system())
+            .add_system(topple_system.system())
+            .add_system(reset_system.system());
+    }
+}
+
+fn setup_spawners(mut commands: Commands) {
+    for spawner in default_lanes() {
+        commands.spawn((spawner,));
     }
 }
 
@@ -35,7 +96,7 @@ fn setup_furnitures(
     mut commands: Commands,
     materials: ResMut<res::ColorMaterialStorage>,
 ) {
-
+    // Shape, size and material are picked when spawned
     let size = Vec2::new(84., 136.); 
 
     for _ in 0..10 {
@@ -43,7 +104,7 @@ fn setup_furnitures(
         commands
             .spawn(SpriteComponents {
                 material: handle.clone(),
-                transform: Transform::from_translation(Vec3::zero()),
+                transform: Transform::from_translation(pool_position()),
                 sprite: Sprite {
                     size,
                     ..Default::default()
@@ -55,7 +116,7 @@ fn setup_furnitures(
                 },
                 ..Default::default()
             })
-            .with(comp::actor::Furniture)
+            .with(comp::actor::Furniture::default())
             .with(comp::physics::ColliderBox {
                 w: size.x(),
                 h: size.y(),
@@ -63,70 +124,178 @@ fn setup_furnitures(
             .with(comp::physics::Velocity(Vec2::zero()))
             .with(comp::stats::Ground)
             .with(comp::stats::Wall)
-            .with(Wave);
+            .with(Wave {
+                end_position: pool_position(),
+            });
     }
 }
 
 fn spawn_system(
-    windows: Res<Windows>,
+    state: Res<res::GameState>,
+    mode: Res<res::GameMode>,
     time: Res<Time>,
-    mut spawn_timer: ResMut<comp::stats::SpawnTimer>,
+    materials: Res<res::ColorMaterialStorage>,
+    mut rng: ResMut<res::GameRng>,
+    mut spawners: Query<&mut FurnitureSpawner>,
     mut query: Query<(
-        With<Wave, &mut Transform>,
+        &mut Wave,
+        &mut comp::actor::Furniture,
+        &mut Transform,
         &mut comp::physics::Velocity,
+        &mut comp::physics::ColliderBox,
+        &mut Sprite,
+        &mut Handle<ColorMaterial>,
         &mut Draw
     )>
 ) {
-    spawn_timer.0.tick(time.delta_seconds);
-    if !spawn_timer.0.finished {
+    if !state.is_playing() {
         return;
     }
 
-    let mut rng = thread_rng();
-    spawn_timer.0.duration = rng.gen_range(2.9, 4.2);
+    for mut spawner in spawners.iter_mut() {
+        spawner.timer.tick(time.delta_seconds);
+        if !spawner.timer.finished {
+            continue;
+        }
+
+        spawner.timer.duration = rng.gen_range(spawner.t_min, spawner.t_max);
+        let (start_position, end_position) = spawner.get_lane(*mode);
 
-    let scr_size = util::get_window_size(windows);
+        for (
+            mut wave,
+            mut furniture,
+            mut transform,
+            mut velocity,
+            mut body,
+            mut sprite,
+            mut material,
+            mut draw,
+        ) in query.iter_mut() {
+            if !draw.is_visible {
+                let shape = FurnitureShape::pick_random(&mut *rng);
+                let size = shape.size();
 
-    for (mut transform, mut velocity, mut draw) in query.iter_mut() {
-        if !draw.is_visible {
-            draw.is_visible = true;
-            *velocity.0.x_mut() = -60.;
-    
-            transform.translation = Vec3::new(
-                scr_size.width / 2. + 200., 
-                -scr_size.height / 2. + 50., 
-                0.
-            );
-
-            // We only want one
-            return;
+                furniture.respawn(shape);
+                body.w = size.x();
+                body.h = size.y();
+                sprite.size = size;
+                if let Some(handle) = materials.storage.get(shape.material_name()) {
+                    *material = handle.clone();
+                }
+
+                draw.is_visible = true;
+                velocity.0 = spawner.get_velocity(start_position, end_position);
+
+                let mut translation = start_position;
+                *translation.y_mut() += size.y() / 2.;
+                transform.translation = translation;
+                wave.end_position = end_position;
+
+                // We only want one
+                break;
+            }
         }
     }
 }
 
+/// Hide furniture that has drifted past the end of its lane
 fn despawn_system(
-    windows: Res<Windows>,
+    state: Res<res::GameState>,
+    mode: Res<res::GameMode>,
+    runner: Res<res::RunnerState>,
     mut query: Query<(
-        With<Wave, &mut Transform>,
+        &Wave,
+        &comp::physics::ColliderBox,
+        &mut Transform,
         &mut Draw,
         &mut comp::physics::Velocity,
     )>,
 ) {
-    let window_size = util::get_window_size(windows);
+    if !state.is_playing() {
+        return;
+    }
 
-    for (mut transform, mut draw, mut velocity) in query.iter_mut() {
-        if transform.translation.x() < -window_size.width / 2. - 200. {
+    let view_left = runner.scroll_x - SCR_WIDTH / 2.;
+
+    for (wave, body, mut transform, mut draw, mut velocity) in query.iter_mut() {
+        if !draw.is_visible {
+            continue;
+        }
+
+        let remaining = (wave.end_position - transform.translation).truncate();
+        let sunk = transform.translation.y() < -SCR_HEIGHT;
+        // Slow furniture can't keep up with the scrolling view
+        let left_behind = *mode == res::GameMode::Runner
+            && transform.translation.x() + body.w / 2. < view_left;
+        if remaining.x() * velocity.x() <= 0. || sunk || left_behind {
             draw.is_visible = false;
+            velocity.0 = Vec2::zero();
+            transform.translation = pool_position();
+        }
+    }
+}
 
-            let start_position = Vec3::new(
-                window_size.width / 2. + 200., 
-                -window_size.height / 2. + 50., 
-                0.
-            );
+/// Topple furniture that has been stood on longer than its shape allows
+fn topple_system(
+    state: Res<res::GameState>,
+    time: Res<Time>,
+    mut collision_event_reader: Local<EventReader<res::GroundCollisionEvent>>,
+    collision_events: Res<Events<res::GroundCollisionEvent>>,
+    mut shake_events: ResMut<Events<res::CameraShakeEvent>>,
+    mut query: Query<(&mut comp::actor::Furniture, &mut comp::physics::Velocity)>,
+) {
+    if !state.is_playing() {
+        return;
+    }
+
+    let mut stood_on: Vec<Entity> = Vec::new();
+    for event in collision_event_reader.iter(&collision_events) {
+        if !stood_on.contains(&event.hit_entity) {
+            stood_on.push(event.hit_entity);
+        }
+    }
 
-            transform.translation = start_position;
+    for entity in stood_on {
+        if let Ok((mut furniture, mut velocity)) = query.get_mut(entity) {
+            let max_stand_time = match furniture.shape.max_stand_time() {
+                Some(max_stand_time) => max_stand_time,
+                None => continue,
+            };
 
-            *velocity.0.x_mut() = 0.;
+            furniture.stand_time += time.delta_seconds;
+            if furniture.stand_time > max_stand_time {
+                if velocity.y() != -TOPPLE_SPEED {
+                    shake_events.send(res::CameraShakeEvent { trauma: 0.3 });
+                }
+                *velocity.0.y_mut() = -TOPPLE_SPEED;
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Hide every furniture and restart the spawn timers on a new run
+fn reset_system(
+    mut restart_event_reader: Local<EventReader<res::RestartEvent>>,
+    restart_events: Res<Events<res::RestartEvent>>,
+    mut spawners: Query<&mut FurnitureSpawner>,
+    mut query: Query<(
+        With<Wave, &mut Draw>,
+        &mut Transform,
+        &mut comp::physics::Velocity,
+    )>,
+) {
+    if restart_event_reader.iter(&restart_events).next().is_none() {
+        return;
+    }
+
+    for mut spawner in spawners.iter_mut() {
+        spawner.timer.reset();
+        spawner.timer.duration = 0.1;
+    }
+
+    for (mut draw, mut transform, mut velocity) in query.iter_mut() {
+        draw.is_visible = false;
+        transform.translation = pool_position();
+        velocity.0 = Vec2::zero();
+    }
+}
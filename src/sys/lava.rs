@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+
+use crate::comp::{particles, physics, stats};
+use crate::res;
+
+use rand::Rng;
+
+const OBSIDIAN_HEIGHT: f32 = 10.;
+/// Part of the lifetime after which the platform cracks and sinks
+const OBSIDIAN_CRACK_AT: f32 = 0.7;
+
+pub struct LavaPlugin;
+
+impl Plugin for LavaPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(cool_lava_system.system())
+            .add_system(obsidian_system.system())
+            .add_system(reset_obsidian_system.system());
+    }
+}
+
+/// Turn lava into an obsidian platform where water projectiles hit it
+fn cool_lava_system(
+    mut commands: Commands,
+    materials: Res<res::ColorMaterialStorage>,
+    mut rng: ResMut<res::CosmeticRng>,
+    mut hit_event_reader: Local<EventReader<res::ProjectileHitEvent>>,
+    hit_events: Res<Events<res::ProjectileHitEvent>>,
+    hazards: Query<(With<stats::Hazard, &Transform>, &physics::ColliderBox)>,
+) {
+    for event in hit_event_reader.iter(&hit_events) {
+        let (lava_transform, lava_body) = match hazards.get(event.hit_entity) {
+            Ok(result) => result,
+            Err(_) => continue,
+        };
+
+        let lava_top = lava_transform.translation.y() + lava_body.h / 2.;
+        let position = Vec2::new(event.contact_point.x(), lava_top + OBSIDIAN_HEIGHT / 2.);
+        let kind = event.projectile_kind;
+
+        spawn_obsidian(
+            &mut commands,
+            &materials,
+            position,
+            kind.platform_width(),
+            kind.platform_time(),
+        );
+        spawn_steam_particle(&mut commands, &materials, &mut rng, position);
+    }
+}
+
+/// Crack and sink obsidian platforms at the end of their life
+fn obsidian_system(
+    mut commands: Commands,
+    state: Res<res::GameState>,
+    time: Res<Time>,
+    materials: Res<res::ColorMaterialStorage>,
+    mut query: Query<(
+        Entity,
+        With<stats::Obsidian, &mut stats::TimeToLive>,
+        &mut physics::Velocity,
+        &mut Handle<ColorMaterial>,
+    )>,
+) {
+    if !state.is_playing() {
+        return;
+    }
+
+    for (entity, mut timer, mut velocity, mut material) in query.iter_mut() {
+        timer.0.tick(time.delta_seconds);
+
+        if timer.0.finished {
+            commands.despawn(entity);
+            continue;
+        }
+
+        let crack_time = timer.0.duration * OBSIDIAN_CRACK_AT;
+        if timer.0.elapsed > crack_time && velocity.y() == 0. {
+            if let Some(handle) = materials.storage.get("Obsidian_Cracked") {
+                *material = handle.clone();
+            }
+
+            // Sink its own height during the rest of its life
+            let sink_time = timer.0.duration - crack_time;
+            *velocity.0.y_mut() = -OBSIDIAN_HEIGHT / sink_time;
+        }
+    }
+}
+
+fn reset_obsidian_system(
+    mut commands: Commands,
+    mut restart_event_reader: Local<EventReader<res::RestartEvent>>,
+    restart_events: Res<Events<res::RestartEvent>>,
+    query: Query<With<stats::Obsidian, Entity>>,
+) {
+    if restart_event_reader.iter(&restart_events).next().is_none() {
+        return;
+    }
+
+    for entity in &mut query.iter() {
+        commands.despawn(entity);
+    }
+}
+
+fn spawn_obsidian(
+    commands: &mut Commands,
+    materials: &res::ColorMaterialStorage,
+    position: Vec2,
+    width: f32,
+    time_to_live: f32,
+) {
+    let size = Vec2::new(width, OBSIDIAN_HEIGHT);
+    let handle = materials.storage.get("Obsidian").unwrap();
+
+    commands
+        .spawn(SpriteComponents {
+            material: handle.clone(),
+            transform: Transform::from_translation(position.extend(1.)),
+            sprite: Sprite {
+                size,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(physics::ColliderBox {
+            w: size.x(),
+            h: size.y(),
+        })
+        .with(physics::Velocity::default())
+        .with(stats::Ground)
+        .with(stats::Obsidian)
+        .with(stats::TimeToLive(Timer::from_seconds(time_to_live, false)));
+}
+
+pub fn spawn_steam_particle(
+    commands: &mut Commands,
+    materials: &res::ColorMaterialStorage,
+    rng: &mut res::CosmeticRng,
+    position: Vec2,
+) {
+    for _ in 0..6 {
+        let x = rng.gen_range(-20., 20.);
+        let y = rng.gen_range(30., 60.);
+
+        let particle = particles::DustParticle {
+            size: Vec2::new(4., 4.),
+        };
+        let handle = materials.storage.get("Steam").unwrap();
+        commands.spawn(SpriteComponents {
+            material: handle.clone(),
+            transform: Transform::from_translation(position.extend(1.)),
+            sprite: Sprite {
+                size: particle.size,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(particle)
+        .with(particles::Particle)
+        .with(particles::Shrinkable)
+        .with(stats::TimeToLive(Timer::from_seconds(0.8, false)))
+        .with(physics::Velocity(Vec2::new(x, y)));
+    }
+}
@@ -4,6 +4,15 @@ pub mod player;
 pub mod stats;
 pub mod furnitures;
 pub mod particles;
+pub mod state;
+pub mod score;
+pub mod lava;
+pub mod weapon;
+pub mod trajectory;
+pub mod camera;
+pub mod runner;
+pub mod options;
+pub mod replay;
 
 use bevy::prelude::*;
 use crate::res;
@@ -13,12 +22,25 @@ pub struct GameLogicPlugin;
 impl Plugin for GameLogicPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app
+            .add_event::<res::DeathEvent>()
+            .add_event::<res::LandingEvent>()
             .init_resource::<res::GroundContactListenerState>()
+            .init_resource::<res::DeathListenerState>()
+            .add_plugin(state::GameStatePlugin)
+            .add_plugin(replay::ReplayPlugin)
+            .add_plugin(options::OptionsPlugin)
             .add_plugin(actor::GameActorPlugin)
             .add_plugin(physics::GamePhysicsPlugin)
             .add_plugin(player::PlayerPlugin)
             .add_plugin(furnitures::FurniturePlugin)
             .add_plugin(particles::ParticlePlugin)
-            .add_system(stats::collider_contact_system.system()); // TODO - add to plugin 
+            .add_plugin(score::ScorePlugin)
+            .add_plugin(lava::LavaPlugin)
+            .add_plugin(weapon::WeaponPlugin)
+            .add_plugin(trajectory::TrajectoryPlugin)
+            .add_plugin(camera::CameraPlugin)
+            .add_plugin(runner::RunnerPlugin)
+            .add_system(stats::collider_contact_system.system()) // TODO - add to plugin 
+            .add_system(stats::respawn_system.system());
     }
 }
\ No newline at end of file
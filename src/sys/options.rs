@@ -0,0 +1,203 @@
+use bevy::{
+    prelude::*,
+    input::gamepad::{GamepadButton, GamepadButtonType},
+};
+use crate::input::{InputAction, InputBinding, InputMap, InputSources};
+use crate::res::{self, GameState};
+
+pub struct OptionsPlugin;
+
+impl Plugin for OptionsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_resource(res::Settings::load())
+            .add_resource(InputMap::load())
+            .init_resource::<OptionsMenu>()
+            .add_startup_system(setup_options_ui_system.system())
+            .add_system(options_input_system.system())
+            .add_system(options_text_system.system());
+    }
+}
+
+/// Rows of the options menu, the trajectory toggle first and then every action
+const ROWS_COUNT: usize = InputAction::ALL.len() + 1;
+
+#[derive(Debug, Default)]
+struct OptionsMenu {
+    selected: usize,
+    /// Action waiting for an input to bind
+    capturing: Option<InputAction>,
+}
+
+impl OptionsMenu {
+    fn selected_action(&self) -> Option<InputAction> {
+        row_action(self.selected)
+    }
+}
+
+/// Action rebound on a row, none for the trajectory toggle
+fn row_action(row: usize) -> Option<InputAction> {
+    match row {
+        0 => None,
+        row => InputAction::ALL.get(row - 1).copied(),
+    }
+}
+
+enum OptionsText {
+    Header,
+    Row(usize),
+    Footer,
+}
+
+fn setup_options_ui_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let font = asset_server.load(res::FONT);
+    let texts = std::iter::once(OptionsText::Header)
+        .chain((0..ROWS_COUNT).map(OptionsText::Row))
+        .chain(std::iter::once(OptionsText::Footer));
+
+    commands
+        .spawn(NodeComponents {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                // Ui grows up, reversed to list the rows top down
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            for text in texts {
+                parent
+                    .spawn(TextComponents {
+                        text: Text {
+                            value: String::new(),
+                            font: font.clone(),
+                            style: TextStyle {
+                                font_size: 16.,
+                                color: Color::WHITE,
+                                ..Default::default()
+                            },
+                        },
+                        ..Default::default()
+                    })
+                    .with(text);
+            }
+        });
+}
+
+/// Navigate the options, toggle settings and rebind actions to the next input pressed
+fn options_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut state: ResMut<GameState>,
+    mut menu: ResMut<OptionsMenu>,
+    mut settings: ResMut<res::Settings>,
+    mut input_map: ResMut<InputMap>,
+) {
+    if *state != GameState::Options {
+        return;
+    }
+
+    let sources = InputSources {
+        keyboard: &keyboard_input,
+        mouse: &mouse_button_input,
+        gamepad_buttons: &gamepad_buttons,
+    };
+
+    if let Some(action) = menu.capturing {
+        let binding = match sources.any_just_pressed() {
+            Some(binding) => binding,
+            None => return,
+        };
+
+        menu.capturing = None;
+        if binding != InputBinding::Key(KeyCode::Escape) {
+            input_map.bind(action, binding);
+            input_map.save();
+        }
+        return;
+    }
+
+    // Menu navigation is fixed so a bad binding can't lock the player out
+    let nav = |key: KeyCode, button: GamepadButtonType| {
+        keyboard_input.just_pressed(key)
+            || gamepad_buttons.get_just_pressed().any(|pressed| pressed.1 == button)
+    };
+
+    if nav(KeyCode::Up, GamepadButtonType::DPadUp) {
+        menu.selected = (menu.selected + ROWS_COUNT - 1) % ROWS_COUNT;
+    } else if nav(KeyCode::Down, GamepadButtonType::DPadDown) {
+        menu.selected = (menu.selected + 1) % ROWS_COUNT;
+    } else if nav(KeyCode::Return, GamepadButtonType::South) {
+        match menu.selected_action() {
+            Some(action) => menu.capturing = Some(action),
+            None => {
+                settings.show_trajectory = !settings.show_trajectory;
+                settings.save();
+            },
+        }
+    } else if nav(KeyCode::Back, GamepadButtonType::West) {
+        if let Some(action) = menu.selected_action() {
+            input_map.clear(action);
+            input_map.save();
+        }
+    } else if nav(KeyCode::Delete, GamepadButtonType::North) {
+        *input_map = InputMap::default();
+        input_map.save();
+    } else if nav(KeyCode::Escape, GamepadButtonType::East) {
+        *state = GameState::Menu;
+    }
+}
+
+fn options_text_system(
+    state: Res<GameState>,
+    menu: Res<OptionsMenu>,
+    settings: Res<res::Settings>,
+    input_map: Res<InputMap>,
+    mut query: Query<(&OptionsText, &mut Text)>,
+) {
+    let visible = *state == GameState::Options;
+
+    for (options_text, mut text) in query.iter_mut() {
+        let value = if !visible {
+            String::new()
+        } else {
+            match options_text {
+                OptionsText::Header => "Options".to_string(),
+                OptionsText::Footer => match menu.capturing {
+                    Some(action) => format!("Press an input for {} - Esc to cancel", action.name()),
+                    None => "Enter rebind - Backspace clear - Delete defaults - Esc back".to_string(),
+                },
+                OptionsText::Row(row) => {
+                    let marker = if *row == menu.selected { "> " } else { "  " };
+                    let line = match row_action(*row) {
+                        Some(action) => format!(
+                            "{}: {}",
+                            action.name(),
+                            input_map.binding_names(action),
+                        ),
+                        None => format!(
+                            "Show trajectory: {}",
+                            if settings.show_trajectory { "On" } else { "Off" },
+                        ),
+                    };
+
+                    format!("{}{}", marker, line)
+                },
+            }
+        };
+
+        if text.value != value {
+            text.value = value;
+        }
+    }
+}
+
@@ -3,18 +3,21 @@ use crate::{
 };
 use crate::comp;
 use crate::res;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 pub struct ParticlePlugin; 
 
 impl Plugin for ParticlePlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_system(shrinkable_particle_cleanup_system.system());
+        app.add_system(shrinkable_particle_cleanup_system.system())
+            .add_system(animation_particle_system.system())
+            .add_system(reset_particles_system.system());
     }
 }
 
 pub fn shrinkable_particle_cleanup_system(
     mut commands: Commands,
+    state: Res<res::GameState>,
     time: Res<Time>,
     mut query: Query<(
         Entity, 
@@ -22,6 +25,10 @@ pub fn shrinkable_particle_cleanup_system(
         With<comp::particles::Shrinkable, &mut comp::stats::TimeToLive>
     )>
 ) {
+    if !state.is_playing() {
+        return;
+    }
+
     for (
         entity, 
         mut sprite, 
@@ -32,7 +39,7 @@ pub fn shrinkable_particle_cleanup_system(
             let procentage = 1. - (timer.0.elapsed) / timer.0.duration;
             sprite.size *= procentage;
 
-            return;
+            continue;
         }
         
         commands.remove_one::<Draw>(entity);
@@ -40,14 +47,122 @@ pub fn shrinkable_particle_cleanup_system(
     }
 }
 
+/// Spawn dust on animation frame events, e.g. footsteps and landings
+pub fn animation_particle_system(
+    mut commands: Commands,
+    mut materials: ResMut<res::ColorMaterialStorage>,
+    mut rng: ResMut<res::CosmeticRng>,
+    mut animation_event_reader: Local<EventReader<res::AnimationEvent>>,
+    animation_events: Res<Events<res::AnimationEvent>>,
+    query: Query<(&Transform, &comp::physics::ColliderBox, &comp::stats::Facing)>,
+) {
+    for event in animation_event_reader.iter(&animation_events) {
+        let (transform, body, facing) = match query.get(event.entity) {
+            Ok(result) => result,
+            Err(_) => continue,
+        };
+
+        let mut position = transform.translation.truncate();
+        *position.y_mut() -= body.get_size().y() / 2.;
+
+        match event.name.as_str() {
+            "footstep" => spawn_footstep_particle(&mut commands, &mut materials, &mut rng, position, facing.0),
+            "land" => spawn_dust_particle(&mut commands, &mut materials, &mut rng, position),
+            _ => {},
+        }
+    }
+}
+
+/// Particles left over from the last run would hang frozen in the new one
+fn reset_particles_system(
+    mut commands: Commands,
+    mut restart_event_reader: Local<EventReader<res::RestartEvent>>,
+    restart_events: Res<Events<res::RestartEvent>>,
+    query: Query<With<comp::particles::DustParticle, Entity>>,
+) {
+    if restart_event_reader.iter(&restart_events).next().is_none() {
+        return;
+    }
+
+    for entity in query.iter() {
+        commands.despawn(entity);
+    }
+}
+
+pub fn spawn_footstep_particle(
+    commands: &mut Commands,
+    materials: &mut res::ColorMaterialStorage,
+    rng: &mut res::CosmeticRng,
+    position: Vec2,
+    facing: f32,
+) {
+    for _ in 0..2 {
+        let x = rng.gen_range(20., 40.);
+        let y = rng.gen_range(10., 30.);
+
+        let particle = comp::particles::DustParticle::default();
+        let handle = materials.storage.get(&"Dust".to_string()).unwrap();
+        commands.spawn(SpriteComponents {
+            material: handle.clone(),
+            transform: Transform::from_translation(position.extend(0.)),
+            sprite: Sprite {
+                size: particle.size,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(particle)
+        .with(comp::particles::Particle)
+        .with(comp::particles::Shrinkable)
+        .with(comp::stats::TimeToLive(Timer::from_seconds(0.3, false)))
+        .with(comp::physics::Velocity(Vec2::new(
+            -facing * x,
+            y,
+        )));
+    }
+}
+
+/// Water drops bouncing off in `direction` from where a projectile hit
+pub fn spawn_splash_particle(
+    commands: &mut Commands,
+    materials: &mut res::ColorMaterialStorage,
+    rng: &mut res::CosmeticRng,
+    position: Vec2,
+    direction: Vec2,
+    particle_count: i32,
+) {
+    for _ in 0..particle_count {
+        let spread = Vec2::new(rng.gen_range(-40., 40.), rng.gen_range(-40., 40.));
+        let speed = rng.gen_range(40., 90.);
+
+        let particle = comp::particles::DustParticle::default();
+        let handle = materials.storage.get(&"Projectile".to_string()).unwrap();
+        commands.spawn(SpriteComponents {
+            material: handle.clone(),
+            transform: Transform::from_translation(position.extend(0.)),
+            sprite: Sprite {
+                size: particle.size,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(particle)
+        .with(comp::particles::Particle)
+        .with(comp::particles::Shrinkable)
+        .with(comp::stats::TimeToLive(Timer::from_seconds(0.4, false)))
+        .with(comp::physics::GravitationalAttraction::default())
+        .with(comp::physics::Velocity(direction * speed + spread));
+    }
+}
+
 pub fn spawn_dust_particle(
     commands: &mut Commands,
     materials: &mut res::ColorMaterialStorage,
+    rng: &mut res::CosmeticRng,
     position: Vec2,
 ) {
     let upper = 140.;
     let lower = -140.;
-    let mut rng = thread_rng();
 
     for _ in 0..5 {
         let x = rng.gen_range(lower, upper);
@@ -75,6 +190,7 @@ pub fn spawn_dust_particle(
 pub fn spawn_wall_dust_particle(
     commands: &mut Commands,
     materials: &mut res::ColorMaterialStorage,
+    rng: &mut res::CosmeticRng,
     position: Vec2,
     direction: Vec2,
     facing: f32,
@@ -82,7 +198,6 @@ pub fn spawn_wall_dust_particle(
 ) {
     let upper = 20.;
     let lower = -20.;
-    let mut rng = thread_rng();
 
     for _ in 0..particle_count {
         let rnd = rng.gen_range(lower, upper);
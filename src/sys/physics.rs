@@ -2,11 +2,9 @@ use bevy::prelude::*;
 
 use crate::comp::{self, physics, stats};
 use crate::res;
+use crate::tuning::Tuning;
 use bevy::sprite::collide_aabb::{collide, Collision};
 
-const FALL_MULTIPLIER: f32 = 2.5;
-const LOW_JUMP_MULTIPLIER: f32 = 2.;
-
 pub struct GamePhysicsPlugin;
 
 impl Plugin for GamePhysicsPlugin {
@@ -26,28 +24,43 @@ impl Plugin for GamePhysicsPlugin {
 
 /// Move entities with Velocity components
 pub fn process_velocity_system(
+    state: Res<res::GameState>,
     time: Res<Time>,
     mut query: Query<(&physics::Velocity, &mut Transform)>,
 ) {
+    if !state.is_playing() {
+        return;
+    }
+
     for (velocity, mut transform) in query.iter_mut() {
         transform.translation += velocity.0.extend(0.) * time.delta_seconds;
     }
 }
 
 pub fn drag_system(
+    state: Res<res::GameState>,
     time: Res<Time>, 
     mut query: Query<(&mut physics::Velocity, &physics::Drag)>) {
+    if !state.is_playing() {
+        return;
+    }
+
     for (mut velocity, drag) in query.iter_mut() {
         *velocity = physics::Velocity(velocity.lerp(Vec2::zero(), time.delta_seconds * drag.0));
     }
 }
 
 pub fn gravity_system(
+    state: Res<res::GameState>,
     gravity: Res<physics::Gravity>,
     time: Res<Time>,
     attraction: &physics::GravitationalAttraction,
     mut velocity: Mut<physics::Velocity>,
 ) {
+    if !state.is_playing() {
+        return;
+    }
+
     if attraction.is_active {
         *velocity.0.y_mut() -= gravity.0 * time.delta_seconds;
     } else {        
@@ -56,34 +69,40 @@ pub fn gravity_system(
 }
 
 fn adjust_jump_system(
+    state: Res<res::GameState>,
     time: Res<Time>,
     gravity: Res<comp::physics::Gravity>,
-    keyboard_input: Res<Input<KeyCode>>,
+    tuning: Res<Tuning>,
     mut query: Query<(
-        &comp::actor::Player, 
+        &comp::actor::Controller, 
         &mut comp::physics::Velocity, 
         &comp::physics::GravitationalAttraction
     )>,
 ) {
+    if !state.is_playing() {
+        return;
+    }
+
     let dt = time.delta_seconds;
 
-    for (_player, mut velocity, affected) in query.iter_mut() {
+    for (controller, mut velocity, affected) in query.iter_mut() {
         if !affected.is_active {
             break;
         }
 
         // Better jumping
         if velocity.0.y() < 0.0 {
-            let vel = Vec2::unit_y() * -gravity.0 * (FALL_MULTIPLIER - 1.) * dt;
+            let vel = Vec2::unit_y() * -gravity.0 * (tuning.fall_multiplier - 1.) * dt;
             velocity.0 += vel;
-        } else if velocity.0.y() > 0.0 && !keyboard_input.pressed(KeyCode::Space) {
-            let vel = Vec2::unit_y() * -gravity.0 * (LOW_JUMP_MULTIPLIER - 1.) * dt;
+        } else if velocity.0.y() > 0.0 && !controller.jump_held {
+            let vel = Vec2::unit_y() * -gravity.0 * (tuning.low_jump_multiplier - 1.) * dt;
             velocity.0 += vel;
         }
     }
 }
 
 fn player_collision_system(
+    state: Res<res::GameState>,
     mut collision_events: ResMut<Events<res::GroundCollisionEvent>>,
     mut query_1: Query<(
         &comp::actor::Player, 
@@ -91,13 +110,18 @@ fn player_collision_system(
         &mut Transform
     )>,
     query_2: Query<(
+        Entity,
         &comp::physics::ColliderBox,
-        Without<comp::actor::Player, &Transform>,
+        Without<comp::actor::Player, Without<comp::actor::Projectile, &Transform>>,
         &comp::physics::Velocity,
     )>,
 ) {
+    if !state.is_playing() {
+        return;
+    }
+
     for (_, body, transform) in query_1.iter_mut() {
-        for (other_body, other_transform, other_velocity) in &mut query_2.iter() {
+        for (other_entity, other_body, other_transform, other_velocity) in &mut query_2.iter() {
             let mut translation = transform.translation.clone();
             *translation.y_mut() -= 1.;
 
@@ -110,6 +134,7 @@ fn player_collision_system(
 
             if let Some(collision) = collision {
                 collision_events.send(res::GroundCollisionEvent {
+                    hit_entity: other_entity,
                     hit_collision: collision,
                     hit_transform: other_transform.clone(),
                     hit_size: other_body.get_size(),
@@ -129,10 +154,10 @@ pub fn update_raycast(
 }
 
 pub fn shoot_raycast(
+    state: Res<res::GameState>,
     mut query1: Query<(
         With<comp::actor::Player, &mut Transform>, 
         &comp::physics::Raycast, 
-        &mut physics::GravitationalAttraction, 
         &comp::physics::ColliderBox,
         &comp::stats::Facing,
         &mut comp::physics::CollisionData,
@@ -140,10 +165,13 @@ pub fn shoot_raycast(
     )>,
     query2: Query<(With<stats::Wall, &Transform>, &physics::ColliderBox, &physics::Velocity)>,
 ) {
+    if !state.is_playing() {
+        return;
+    }
+
     for (
         mut transform, 
         raycast, 
-        mut attraction, 
         p_box, 
         facing, 
         mut collision_data,
@@ -153,18 +181,16 @@ pub fn shoot_raycast(
         *position.x_mut() = position.x() + (p_box.get_size().x() / 2. * facing.0);
 
         let size = Vec2::new(12., 1.);
+
+        // Touching a wall is found again every frame, gravity stays on
+        // and wall sliding caps the fall speed
+        collision_data.right = false;
+        collision_data.left = false;
         
         for (other_transform, other_box, other_velocity) in &mut query2.iter() {
             if let Some(collision) = collide(position, size, other_transform.translation, other_box.get_size()) {
-                
-                collision_data.right = false;
-                collision_data.left = false;
-
-                attraction.is_active = true;
                 match collision {
                     Collision::Left => {
-                        attraction.is_active = false;
-
                         let mut translation = transform.translation;
 
                         if translation.x() + p_box.get_size().x() / 2. > other_transform.translation.x() - other_box.get_size().x() / 2. {
@@ -177,8 +203,6 @@ pub fn shoot_raycast(
                         *velocity.0.x_mut() = other_velocity.0.x();
                     },
                     Collision::Right => {
-                        attraction.is_active = false;
-
                         let mut translation = transform.translation;
 
                         if translation.x() - p_box.get_size().x() / 2. < other_transform.translation.x() + other_box.get_size().x() / 2. {
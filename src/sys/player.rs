@@ -1,10 +1,13 @@
 use crate::comp;
+use crate::res;
+use crate::input::{InputAction, InputMap, InputOwner, InputSources};
 use crate::util::*;
 
 use bevy::{
     prelude::*,
     input::{keyboard::KeyCode, Input},
-    input::mouse::MouseButtonInput,
+    input::gamepad::{GamepadAxis, GamepadAxisType, GamepadButton, GamepadEvent, GamepadEventType},
+    input::mouse::{MouseButtonInput, MouseWheel},
     window::CursorMoved,
 };
 
@@ -13,6 +16,8 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.init_resource::<MouseState>()
+            .init_resource::<res::PlayerIds>()
+            .add_system(gamepad_connection_system.system())
             .add_system(handle_input_system.system());
     }
 }
@@ -21,56 +26,181 @@ impl Plugin for PlayerPlugin {
 pub struct MouseState {
     mouse_button_event_reader: EventReader<MouseButtonInput>,
     cursor_moved_event_reader: EventReader<CursorMoved>,
+    mouse_wheel_event_reader: EventReader<MouseWheel>,
 }
 
-/// Converts real player input into Controller input
+/// Stick deflection ignored for movement
+const MOVEMENT_DEAD_ZONE: f32 = 0.2;
+/// Stick deflection needed before aiming, smaller deflections keep the last aim
+const AIM_DEAD_ZONE: f32 = 0.5;
+
+/// Pressing a button on a gamepad no player owns claims the free player with
+/// the lowest id, disconnecting the pad frees the player again
+fn gamepad_connection_system(
+    mut gamepad_event_reader: Local<EventReader<GamepadEvent>>,
+    gamepad_events: Res<Events<GamepadEvent>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut query: Query<&mut comp::actor::Player>,
+) {
+    for event in gamepad_event_reader.iter(&gamepad_events) {
+        if let GamepadEvent(gamepad, GamepadEventType::Disconnected) = event {
+            for mut player in query.iter_mut() {
+                if player.gamepad == Some(*gamepad) {
+                    player.gamepad = None;
+                }
+            }
+        }
+    }
+
+    for GamepadButton(gamepad, _) in gamepad_buttons.get_just_pressed() {
+        let owned = query.iter_mut().any(|player| player.gamepad == Some(*gamepad));
+        if owned {
+            continue;
+        }
+
+        let free_player = query
+            .iter_mut()
+            .filter(|player| player.gamepad.is_none())
+            .min_by_key(|player| player.id);
+
+        if let Some(mut player) = free_player {
+            player.gamepad = Some(*gamepad);
+        }
+    }
+}
+
+/// Converts real player input into Controller input through the input map
 pub fn handle_input_system(
+    game_state: Res<res::GameState>,
+    replay: Res<res::ReplayState>,
     windows: Res<Windows>,
+    input_map: Res<InputMap>,
     mut state: ResMut<MouseState>,
     mouse_button_input: Res<Input<MouseButton>>,
     cursor_moved_events: Res<Events<CursorMoved>>,
+    mouse_wheel_events: Res<Events<MouseWheel>>,
     keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    cameras: Query<(With<comp::actor::MainCamera, &Transform>, &OrthographicProjection)>,
     mut query: Query<(
-        With<comp::actor::Player, &mut comp::actor::Controller>, 
+        &comp::actor::Player,
+        &mut comp::actor::Controller, 
         &comp::physics::CollisionData,
         &comp::stats::Grounded,
     )>,
 ) {
+    // Replays feed the controllers themselves
+    if !game_state.is_playing() || replay.is_playback() {
+        return;
+    }
+
     let window_size = get_window_size(windows);
+    let cursor_moved = state.cursor_moved_event_reader.iter(&cursor_moved_events).last().map(|event| event.position);
+    let wheel_steps: Vec<i32> = state.mouse_wheel_event_reader
+        .iter(&mouse_wheel_events)
+        .filter(|event| event.y != 0.)
+        .map(|event| if event.y > 0. { -1 } else { 1 })
+        .collect();
+
+    let sources = InputSources {
+        keyboard: &keyboard_input,
+        mouse: &mouse_button_input,
+        gamepad_buttons: &gamepad_buttons,
+    };
 
     for (
+        player,
         mut controller, 
         collision_data, 
         grounded
     ) in query.iter_mut() {
-        if mouse_button_input.pressed(MouseButton::Left) { 
+        let owner = InputOwner {
+            keyboard_mouse: player.id == 0,
+            gamepad: player.gamepad,
+            any_gamepad: false,
+        };
+        let pressed = |action| input_map.pressed(action, &sources, owner);
+        let just_pressed = |action| input_map.just_pressed(action, &sources, owner);
+
+        if pressed(InputAction::Shoot) { 
             controller.action
                 .push_back(comp::actor::ControllerAction::Shoot);
         }
 
-        for event in state.cursor_moved_event_reader.iter(&cursor_moved_events) {
-            let cursor_position = event.position - Vec2::new(window_size.width / 2., window_size.height / 2.);
-            *controller.cursor_position.x_mut() = cursor_position.x();
-            *controller.cursor_position.y_mut() = cursor_position.y();
+        if owner.keyboard_mouse {
+            if let Some(position) = cursor_moved {
+                controller.cursor_screen_position = position;
+                controller.aim_direction = None;
+            }
+
+            // The camera can move without the cursor moving, so map it every frame
+            for (camera_transform, projection) in &mut cameras.iter() {
+                controller.cursor_position = screen_to_world(
+                    controller.cursor_screen_position,
+                    window_size,
+                    camera_transform,
+                    projection,
+                );
+            }
+
+            for steps in wheel_steps.iter() {
+                controller.action
+                    .push_back(comp::actor::ControllerAction::CycleWeapon(*steps));
+            }
         }
 
-        if keyboard_input.pressed(KeyCode::W) || keyboard_input.pressed(KeyCode::Up) {
+        for (action, kind) in InputAction::WEAPONS.iter().zip(comp::actor::WeaponKind::ALL.iter()) {
+            if just_pressed(*action) {
+                controller.action
+                    .push_back(comp::actor::ControllerAction::SelectWeapon(*kind));
+            }
+        }
+
+        if just_pressed(InputAction::PreviousWeapon) {
+            controller.action
+                .push_back(comp::actor::ControllerAction::CycleWeapon(-1));
+        }
+
+        if just_pressed(InputAction::NextWeapon) {
+            controller.action
+                .push_back(comp::actor::ControllerAction::CycleWeapon(1));
+        }
+
+        if pressed(InputAction::MoveUp) {
             *controller.movement.y_mut() += 1.0;
         }
 
-        if keyboard_input.pressed(KeyCode::S) || keyboard_input.pressed(KeyCode::Down) {
+        if pressed(InputAction::MoveDown) {
             *controller.movement.y_mut() -= 1.0;
         }
         
-        if keyboard_input.pressed(KeyCode::A) || keyboard_input.pressed(KeyCode::Left) {
+        if pressed(InputAction::MoveLeft) {
             *controller.movement.x_mut() -= 1.0;
         }
 
-        if keyboard_input.pressed(KeyCode::D) || keyboard_input.pressed(KeyCode::Right) {
+        if pressed(InputAction::MoveRight) {
             *controller.movement.x_mut() += 1.0;
         }
 
-        if keyboard_input.just_pressed(KeyCode::Space) {
+        if let Some(gamepad) = player.gamepad {
+            let stick = |x_axis, y_axis| Vec2::new(
+                gamepad_axes.get(GamepadAxis(gamepad, x_axis)).unwrap_or(0.),
+                gamepad_axes.get(GamepadAxis(gamepad, y_axis)).unwrap_or(0.),
+            );
+
+            let movement = stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY);
+            controller.movement += apply_dead_zone(movement, MOVEMENT_DEAD_ZONE);
+
+            let aim = stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY);
+            if aim.length() > AIM_DEAD_ZONE {
+                controller.aim_direction = Some(aim.normalize());
+            }
+        }
+
+        controller.jump_held = pressed(InputAction::Jump);
+
+        if just_pressed(InputAction::Jump) {
             if collision_data.either_side() && !grounded.0 {
                 controller.action
                     .push_back(comp::actor::ControllerAction::WallJump);
@@ -80,4 +210,34 @@ pub fn handle_input_system(
             }
         }
     }   
-}
\ No newline at end of file
+}
+
+/// Zero small stick deflections and rescale the rest to start from 0
+fn apply_dead_zone(stick: Vec2, dead_zone: f32) -> Vec2 {
+    let length = stick.length();
+    if length <= dead_zone {
+        return Vec2::zero();
+    }
+
+    stick / length * ((length - dead_zone) / (1. - dead_zone)).min(1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dead_zone_zeroes_small_deflections() {
+        assert_eq!(apply_dead_zone(Vec2::new(0.1, 0.1), 0.2), Vec2::zero());
+    }
+
+    #[test]
+    fn dead_zone_rescales_from_its_edge() {
+        let stick = apply_dead_zone(Vec2::new(0.6, 0.), 0.2);
+        assert!((stick.x() - 0.5).abs() < 1e-6);
+        assert_eq!(stick.y(), 0.);
+
+        let full = apply_dead_zone(Vec2::new(0., -1.), 0.2);
+        assert!((full.y() + 1.).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,175 @@
+use bevy::{
+    prelude::*,
+    input::gamepad::GamepadButton,
+};
+use std::time::Duration;
+use crate::comp::actor;
+use crate::input::{InputAction, InputMap, InputOwner, InputSources};
+use crate::res::{self, GameState, ReplayState};
+
+/// Records every run and plays the last one back. Added before the game
+/// systems so a run starting this frame is seeded before anything rolls
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<res::GameRng>()
+            .init_resource::<res::CosmeticRng>()
+            .init_resource::<ReplayState>()
+            .init_resource::<FrameStart>()
+            .add_system_to_stage(stage::FIRST, replay_frame_system.system())
+            .add_system(replay_control_system.system());
+    }
+}
+
+/// Whether the game was playing when the frame started
+#[derive(Debug, Default)]
+struct FrameStart {
+    playing: bool,
+}
+
+/// Record the controllers every player reads this frame, or overwrite them
+/// and the frame time from the replay being watched
+fn replay_frame_system(
+    mut state: ResMut<GameState>,
+    mut time: ResMut<Time>,
+    mut replay_state: ResMut<ReplayState>,
+    mut frame_start: ResMut<FrameStart>,
+    mut query: Query<(&actor::Player, &mut actor::Controller)>,
+) {
+    frame_start.playing = state.is_playing();
+
+    let finished = match &mut *replay_state {
+        ReplayState::Idle => false,
+        ReplayState::Recording(replay) => {
+            if state.is_playing() {
+                let mut controllers: Vec<(usize, actor::Controller)> = query
+                    .iter_mut()
+                    .map(|(player, controller)| (player.id, controller.clone()))
+                    .collect();
+                controllers.sort_by_key(|(id, _)| *id);
+
+                replay.frames.push(res::ReplayFrame {
+                    delta_seconds: time.delta_seconds,
+                    controllers,
+                });
+                false
+            } else if *state == GameState::GameOver {
+                replay.save();
+                true
+            } else {
+                false
+            }
+        },
+        ReplayState::Playback { replay, frame } => {
+            if state.is_playing() {
+                match replay.frames.get(*frame) {
+                    Some(recorded) => {
+                        set_delta_seconds(&mut time, recorded.delta_seconds);
+
+                        for (player, mut controller) in query.iter_mut() {
+                            let recorded = recorded.controllers
+                                .iter()
+                                .find(|(id, _)| *id == player.id);
+
+                            if let Some((_, recorded)) = recorded {
+                                // The real cursor stays put for when the replay ends
+                                let cursor_screen_position = controller.cursor_screen_position;
+                                *controller = recorded.clone();
+                                controller.cursor_screen_position = cursor_screen_position;
+                            }
+                        }
+
+                        *frame += 1;
+                        false
+                    },
+                    None => {
+                        *state = GameState::Menu;
+                        true
+                    },
+                }
+            } else {
+                *state == GameState::GameOver
+            }
+        },
+    };
+
+    if finished {
+        *replay_state = ReplayState::Idle;
+    }
+}
+
+/// Watch the saved replay from the menus, and start recording or playing
+/// back when a run starts. Runs abandoned through a restart aren't saved
+fn replay_control_system(
+    input_map: Res<InputMap>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    frame_start: Res<FrameStart>,
+    mut state: ResMut<GameState>,
+    mut mode: ResMut<res::GameMode>,
+    mut time: ResMut<Time>,
+    mut rng: ResMut<res::GameRng>,
+    mut cosmetic_rng: ResMut<res::CosmeticRng>,
+    mut replay_state: ResMut<ReplayState>,
+    mut restart_event_reader: Local<EventReader<res::RestartEvent>>,
+    mut restart_events: ResMut<Events<res::RestartEvent>>,
+    mut query: Query<&mut actor::Controller>,
+) {
+    let sources = InputSources {
+        keyboard: &keyboard_input,
+        mouse: &mouse_button_input,
+        gamepad_buttons: &gamepad_buttons,
+    };
+
+    let mut watch = None;
+    let in_menu = *state == GameState::Menu || *state == GameState::GameOver;
+    if in_menu && input_map.just_pressed(InputAction::WatchReplay, &sources, InputOwner::ANY) {
+        let replay = res::Replay::load();
+        // Nothing to watch before the first run has been saved
+        if !replay.frames.is_empty() {
+            *mode = replay.mode;
+            *state = GameState::Playing;
+            restart_events.send(res::RestartEvent);
+            watch = Some(replay);
+        }
+    }
+
+    if restart_event_reader.iter(&restart_events).next().is_some() {
+        *replay_state = match watch {
+            Some(replay) => {
+                rng.reseed(replay.seed);
+                ReplayState::Playback { replay, frame: 0 }
+            },
+            None => {
+                rng.reseed(rand::random());
+                ReplayState::Recording(res::Replay {
+                    seed: rng.seed,
+                    mode: *mode,
+                    frames: Vec::new(),
+                })
+            },
+        };
+        cosmetic_rng.reseed(rng.seed);
+
+        // Input left over from the last run would only be read by this one
+        for mut controller in query.iter_mut() {
+            controller.action.clear();
+            controller.reset_movement();
+        }
+    }
+
+    // A run starting or resuming this frame holds still until the next one,
+    // the first systems of the frame ran before the game was playing
+    let active = !matches!(*replay_state, ReplayState::Idle);
+    if active && state.is_playing() && !frame_start.playing {
+        set_delta_seconds(&mut time, 0.);
+    }
+}
+
+fn set_delta_seconds(time: &mut Time, delta_seconds: f32) {
+    time.delta = Duration::from_secs_f32(delta_seconds);
+    time.delta_seconds = delta_seconds;
+    time.delta_seconds_f64 = delta_seconds as f64;
+}
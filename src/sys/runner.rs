@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+
+use crate::comp::{actor, physics, stats};
+use crate::res;
+use crate::sys::furnitures::FurnitureSpawner;
+use crate::util::SCR_WIDTH;
+
+/// Distance recycled entities are moved ahead, the level repeats every span
+const RECYCLE_SPAN: f32 = SCR_WIDTH;
+
+pub struct RunnerPlugin;
+
+impl Plugin for RunnerPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<res::RunnerState>()
+            .add_system(scroll_system.system())
+            .add_system(recycle_system.system())
+            .add_system(follow_view_system.system())
+            .add_system(left_behind_system.system())
+            .add_system(reset_runner_system.system());
+    }
+}
+
+/// Scroll the view right at an increasing speed, furniture lanes move along with it
+fn scroll_system(
+    state: Res<res::GameState>,
+    mode: Res<res::GameMode>,
+    time: Res<Time>,
+    mut runner: ResMut<res::RunnerState>,
+    mut score: ResMut<res::Score>,
+    mut spawners: Query<&mut FurnitureSpawner>,
+    mut cameras: Query<&mut actor::CameraFollow>,
+) {
+    if !state.is_playing() || *mode != res::GameMode::Runner {
+        return;
+    }
+
+    let dt = time.delta_seconds;
+    runner.speed = (runner.speed + res::RunnerState::ACCELERATION * dt).min(res::RunnerState::MAX_SPEED);
+
+    let delta = runner.speed * dt;
+    runner.scroll_x += delta;
+    score.distance = runner.scroll_x;
+
+    for mut spawner in spawners.iter_mut() {
+        *spawner.start_position.x_mut() += delta;
+        *spawner.end_position.x_mut() += delta;
+    }
+
+    for mut follow in cameras.iter_mut() {
+        follow.lock_x = Some(runner.scroll_x);
+    }
+}
+
+/// Move walls the view has scrolled past ahead of it
+fn recycle_system(
+    state: Res<res::GameState>,
+    runner: Res<res::RunnerState>,
+    mut query: Query<(&stats::Recyclable, &physics::ColliderBox, &mut Transform)>,
+) {
+    if !state.is_playing() {
+        return;
+    }
+
+    let view_left = runner.scroll_x - SCR_WIDTH / 2.;
+
+    for (_, body, mut transform) in query.iter_mut() {
+        if transform.translation.x() + body.w / 2. < view_left {
+            *transform.translation.x_mut() += RECYCLE_SPAN;
+        }
+    }
+}
+
+fn follow_view_system(
+    runner: Res<res::RunnerState>,
+    mut query: Query<(&stats::FollowView, &mut Transform)>,
+) {
+    for (follow, mut transform) in query.iter_mut() {
+        transform.translation.set_x(runner.scroll_x + follow.0);
+    }
+}
+
+/// Falling behind the left edge of the view is death
+fn left_behind_system(
+    state: Res<res::GameState>,
+    mode: Res<res::GameMode>,
+    runner: Res<res::RunnerState>,
+    mut death_events: ResMut<Events<res::DeathEvent>>,
+    query: Query<(Entity, With<actor::Player, &Transform>, &physics::ColliderBox)>,
+) {
+    if !state.is_playing() || *mode != res::GameMode::Runner {
+        return;
+    }
+
+    let view_left = runner.scroll_x - SCR_WIDTH / 2.;
+
+    for (entity, transform, body) in &mut query.iter() {
+        if transform.translation.x() + body.w / 2. < view_left {
+            death_events.send(res::DeathEvent { entity });
+        }
+    }
+}
+
+/// Put the view, lanes and walls back at the start of the level
+fn reset_runner_system(
+    mut restart_event_reader: Local<EventReader<res::RestartEvent>>,
+    restart_events: Res<Events<res::RestartEvent>>,
+    mut runner: ResMut<res::RunnerState>,
+    mut spawners: Query<&mut FurnitureSpawner>,
+    mut cameras: Query<&mut actor::CameraFollow>,
+    mut recyclables: Query<(&stats::Recyclable, &mut Transform)>,
+) {
+    if restart_event_reader.iter(&restart_events).next().is_none() {
+        return;
+    }
+
+    for mut spawner in spawners.iter_mut() {
+        *spawner.start_position.x_mut() -= runner.scroll_x;
+        *spawner.end_position.x_mut() -= runner.scroll_x;
+    }
+
+    for mut follow in cameras.iter_mut() {
+        follow.lock_x = None;
+        follow.position = Vec2::zero();
+    }
+
+    for (recyclable, mut transform) in recyclables.iter_mut() {
+        transform.translation.set_x(recyclable.start_x);
+    }
+
+    *runner = res::RunnerState::default();
+}
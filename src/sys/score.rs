@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+use crate::comp::actor;
+use crate::res;
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<res::Score>()
+            .add_resource(res::HighScores::load())
+            .add_startup_system(setup_score_ui_system.system())
+            .add_system(survival_time_system.system())
+            .add_system(furniture_landing_score_system.system())
+            .add_system(wall_jump_score_system.system())
+            .add_system(high_score_system.system())
+            .add_system(reset_score_system.system())
+            .add_system(score_text_system.system());
+    }
+}
+
+struct ScoreText;
+
+fn setup_score_ui_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands
+        .spawn(TextComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    top: Val::Px(10.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                value: String::new(),
+                font: asset_server.load(res::FONT),
+                style: TextStyle {
+                    font_size: 18.,
+                    color: Color::WHITE,
+                    ..Default::default()
+                },
+            },
+            ..Default::default()
+        })
+        .with(ScoreText);
+}
+
+fn survival_time_system(
+    state: Res<res::GameState>,
+    time: Res<Time>,
+    mut score: ResMut<res::Score>,
+) {
+    if !state.is_playing() {
+        return;
+    }
+
+    score.time += time.delta_seconds;
+}
+
+/// Count every furniture piece the player lands on, landing on the same
+/// piece again only counts once until it is spawned again
+fn furniture_landing_score_system(
+    mut landing_event_reader: Local<EventReader<res::LandingEvent>>,
+    landing_events: Res<Events<res::LandingEvent>>,
+    mut score: ResMut<res::Score>,
+    mut furnitures: Query<&mut actor::Furniture>,
+) {
+    for event in landing_event_reader.iter(&landing_events) {
+        if let Ok(mut furniture) = furnitures.get_mut(event.hit_entity) {
+            if furniture.land() {
+                score.furniture_landings += 1;
+            }
+        }
+    }
+}
+
+fn wall_jump_score_system(
+    mut wall_jump_event_reader: Local<EventReader<res::WallJumpEvent>>,
+    wall_jump_events: Res<Events<res::WallJumpEvent>>,
+    mut score: ResMut<res::Score>,
+) {
+    for _ in wall_jump_event_reader.iter(&wall_jump_events) {
+        score.wall_jumps += 1;
+    }
+}
+
+/// Store the finished run when the game is over
+fn high_score_system(
+    state: ChangedRes<res::GameState>,
+    score: Res<res::Score>,
+    replay: Res<res::ReplayState>,
+    mut high_scores: ResMut<res::HighScores>,
+) {
+    // A watched replay already counted when it was played
+    if *state != res::GameState::GameOver || replay.is_playback() {
+        return;
+    }
+
+    high_scores.insert(res::HighScoreEntry::new(&score));
+    high_scores.save();
+}
+
+fn reset_score_system(
+    mut restart_event_reader: Local<EventReader<res::RestartEvent>>,
+    restart_events: Res<Events<res::RestartEvent>>,
+    mode: Res<res::GameMode>,
+    mut score: ResMut<res::Score>,
+) {
+    if restart_event_reader.iter(&restart_events).next().is_some() {
+        score.reset(*mode);
+    }
+}
+
+fn score_text_system(
+    score: Res<res::Score>,
+    high_scores: Res<res::HighScores>,
+    mut query: Query<With<ScoreText, &mut Text>>,
+) {
+    for mut text in query.iter_mut() {
+        text.value = match score.mode {
+            res::GameMode::Arena => format!(
+                "Score: {}  Time: {:.1}s  Best: {}",
+                score.total(),
+                score.time,
+                high_scores.best(score.mode),
+            ),
+            res::GameMode::Runner => format!(
+                "Distance: {:.0}m  Best: {}m",
+                score.get_meters(),
+                high_scores.best(score.mode),
+            ),
+        };
+    }
+}
@@ -0,0 +1,156 @@
+use bevy::{
+    prelude::*,
+    input::gamepad::GamepadButton,
+};
+use crate::input::{InputAction, InputMap, InputOwner, InputSources};
+use crate::res::{self, GameState};
+
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<GameState>()
+            .init_resource::<res::GameMode>()
+            .add_event::<res::RestartEvent>()
+            .add_startup_system(setup_state_ui_system.system())
+            .add_system(state_input_system.system())
+            .add_system(game_over_system.system())
+            .add_system(state_text_system.system());
+    }
+}
+
+struct StateText;
+
+fn setup_state_ui_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands
+        .spawn(UiCameraComponents::default())
+        .spawn(NodeComponents {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(TextComponents {
+                    text: Text {
+                        value: String::new(),
+                        font: asset_server.load(res::FONT),
+                        style: TextStyle {
+                            font_size: 24.,
+                            color: Color::WHITE,
+                            ..Default::default()
+                        },
+                    },
+                    ..Default::default()
+                })
+                .with(StateText);
+        });
+}
+
+/// Move between menu, playing, paused and game over on input map actions,
+/// the options menu handles its own input
+fn state_input_system(
+    input_map: Res<InputMap>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut state: ResMut<GameState>,
+    mut mode: ResMut<res::GameMode>,
+    mut restart_events: ResMut<Events<res::RestartEvent>>,
+) {
+    let sources = InputSources {
+        keyboard: &keyboard_input,
+        mouse: &mouse_button_input,
+        gamepad_buttons: &gamepad_buttons,
+    };
+    let just_pressed = |action| input_map.just_pressed(action, &sources, InputOwner::ANY);
+
+    match *state {
+        GameState::Menu | GameState::GameOver => {
+            if just_pressed(InputAction::Start) {
+                restart_events.send(res::RestartEvent);
+                *state = GameState::Playing;
+            } else if just_pressed(InputAction::ChangeMode) {
+                *mode = mode.next();
+            } else if *state == GameState::Menu && just_pressed(InputAction::Options) {
+                *state = GameState::Options;
+            }
+        },
+        GameState::Options => {},
+        GameState::Playing => {
+            if just_pressed(InputAction::Pause) {
+                *state = GameState::Paused;
+            }
+        },
+        GameState::Paused => {
+            if just_pressed(InputAction::Pause) {
+                *state = GameState::Playing;
+            } else if just_pressed(InputAction::Restart) {
+                restart_events.send(res::RestartEvent);
+                *state = GameState::Playing;
+            }
+        },
+    }
+}
+
+fn game_over_system(
+    mut death_event_reader: Local<EventReader<res::DeathEvent>>,
+    death_events: Res<Events<res::DeathEvent>>,
+    mut state: ResMut<GameState>,
+) {
+    if death_event_reader.iter(&death_events).next().is_some() && state.is_playing() {
+        *state = GameState::GameOver;
+    }
+}
+
+fn state_text_system(
+    state: Res<GameState>,
+    mode: Res<res::GameMode>,
+    input_map: Res<InputMap>,
+    mut query: Query<With<StateText, &mut Text>>,
+) {
+    for mut text in query.iter_mut() {
+        let message = state_message(*state, *mode, &input_map);
+        if text.value != message {
+            text.value = message;
+        }
+    }
+}
+
+/// Hints name the inputs currently bound, so they stay right after rebinding
+fn state_message(state: GameState, mode: res::GameMode, input_map: &InputMap) -> String {
+    let input = |action| input_map.binding_names(action);
+
+    match state {
+        GameState::Menu => format!(
+            "Oh no, lava! Press {} to start - {} mode: {} - {} options - {} replay",
+            input(InputAction::Start),
+            input(InputAction::ChangeMode),
+            mode.name(),
+            input(InputAction::Options),
+            input(InputAction::WatchReplay),
+        ),
+        GameState::Options | GameState::Playing => String::new(),
+        GameState::Paused => format!(
+            "Paused - {} to resume, {} to restart",
+            input(InputAction::Pause),
+            input(InputAction::Restart),
+        ),
+        GameState::GameOver => format!(
+            "Game over - press {} to try again - {} mode: {} - {} replay",
+            input(InputAction::Start),
+            input(InputAction::ChangeMode),
+            mode.name(),
+            input(InputAction::WatchReplay),
+        ),
+    }
+}
@@ -2,17 +2,18 @@ use crate::comp::actor;
 use crate::comp::physics;
 use crate::comp::stats;
 use crate::res;
-use crate::sys;
 
 use bevy::prelude::*;   
 use bevy::sprite::collide_aabb::Collision;
 
 pub fn collider_contact_system(
-    mut commands: Commands,
     collision_events: Res<Events<res::GroundCollisionEvent>>,
-    mut materials: ResMut<res::ColorMaterialStorage>,
     mut collision_event_reader: ResMut<res::GroundContactListenerState>,
+    mut death_events: ResMut<Events<res::DeathEvent>>,
+    mut landing_events: ResMut<Events<res::LandingEvent>>,
+    hazards: Query<&stats::Hazard>,
     mut query: Query<(
+        Entity,
         &actor::Player, 
         &mut stats::Grounded,
         &physics::ColliderBox, 
@@ -23,6 +24,7 @@ pub fn collider_contact_system(
     )>,
 ) {
     for (
+        entity,
         _player, 
         mut grounded, 
         body, 
@@ -33,9 +35,17 @@ pub fn collider_contact_system(
     ) in query.iter_mut() {
         attraction.is_active = true;
         collision_data.below = false;
-        let prev_below = grounded.0;
+        let was_grounded = grounded.0;
+        // Set again below while standing on something, so walking off a ledge ungrounds
+        grounded.0 = false;
+        let mut standing_on = None;
 
         for event in collision_event_reader.event_reader.iter(&collision_events) {
+            if hazards.get(event.hit_entity).is_ok() {
+                death_events.send(res::DeathEvent { entity });
+                break;
+            }
+
             collision_data.reset();
             collision_data.below = set_grounded_if_needed(
                 &event,
@@ -46,22 +56,62 @@ pub fn collider_contact_system(
             );
 
             if collision_data.below {
-                if !prev_below {
-                    let mut translation = transform.translation.truncate();
-                    *translation.y_mut() -= body.get_size().y() / 2.;
-                    sys::particles::spawn_dust_particle(
-                        &mut commands, 
-                        &mut materials, 
-                        translation
-                    );
-                } 
-
                 *velocity.0.x_mut() = event.hit_velocity.0.x();
+                standing_on = Some(event.hit_entity);
+            }
+        }
+
+        if let Some(hit_entity) = standing_on {
+            if !was_grounded {
+                landing_events.send(res::LandingEvent { entity, hit_entity });
             }
         }
     }
 }
 
+/// Move dead players back to their start position, or every player on restart
+pub fn respawn_system(
+    death_events: Res<Events<res::DeathEvent>>,
+    mut death_event_reader: ResMut<res::DeathListenerState>,
+    restart_events: Res<Events<res::RestartEvent>>,
+    mut restart_event_reader: Local<EventReader<res::RestartEvent>>,
+    mut query: Query<(
+        Entity,
+        &stats::StartPosition,
+        &mut Transform,
+        &mut physics::Velocity,
+        &mut stats::Grounded,
+        &mut physics::GravitationalAttraction,
+        &mut physics::CollisionData,
+    )>,
+) {
+    let restart = restart_event_reader.iter(&restart_events).next().is_some();
+    let dead: Vec<Entity> = death_event_reader.event_reader
+        .iter(&death_events)
+        .map(|event| event.entity)
+        .collect();
+
+    for (
+        entity,
+        start_position,
+        mut transform,
+        mut velocity,
+        mut grounded,
+        mut attraction,
+        mut collision_data,
+    ) in query.iter_mut() {
+        if !restart && !dead.contains(&entity) {
+            continue;
+        }
+
+        transform.translation = start_position.0.extend(transform.translation.z());
+        velocity.0 = Vec2::zero();
+        grounded.0 = false;
+        attraction.is_active = true;
+        collision_data.reset();
+    }
+}
+
 fn set_grounded_if_needed(
     event: &res::GroundCollisionEvent,
     size: &Vec2,
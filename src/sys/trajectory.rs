@@ -0,0 +1,152 @@
+use bevy::{
+    prelude::*,
+    input::gamepad::GamepadButton,
+};
+
+use crate::comp::{actor, physics};
+use crate::input::{InputAction, InputMap, InputOwner, InputSources};
+use crate::{res, util};
+
+/// Dots along each arc
+const DOT_COUNT: usize = 16;
+/// Seconds of flight between two dots
+const DOT_TIME_STEP: f32 = 0.06;
+const DOT_SIZE: f32 = 3.;
+const BOUND_DOT_SIZE: f32 = 2.;
+
+pub struct TrajectoryPlugin;
+
+impl Plugin for TrajectoryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(setup_trajectory_system.system())
+            .add_system(toggle_trajectory_system.system())
+            .add_system(trajectory_system.system());
+    }
+}
+
+/// Which arc a dot belongs to, the bounds show the reach of the spread
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Arc {
+    Center,
+    LowerBound,
+    UpperBound,
+}
+
+/// Dot of the trajectory preview
+struct TrajectoryDot {
+    arc: Arc,
+    index: usize,
+}
+
+fn setup_trajectory_system(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let center_material = materials.add(Color::rgba(1., 1., 1., 0.7).into());
+    let bound_material = materials.add(Color::rgba(1., 1., 1., 0.3).into());
+
+    for arc in [Arc::Center, Arc::LowerBound, Arc::UpperBound].iter() {
+        let (material, size) = match arc {
+            Arc::Center => (center_material.clone(), DOT_SIZE),
+            _ => (bound_material.clone(), BOUND_DOT_SIZE),
+        };
+
+        for index in 0..DOT_COUNT {
+            commands
+                .spawn(SpriteComponents {
+                    material: material.clone(),
+                    sprite: Sprite {
+                        size: Vec2::new(size, size),
+                        ..Default::default()
+                    },
+                    draw: Draw {
+                        is_visible: false,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with(TrajectoryDot {
+                    arc: *arc,
+                    index,
+                });
+        }
+    }
+}
+
+fn toggle_trajectory_system(
+    state: Res<res::GameState>,
+    input_map: Res<InputMap>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut settings: ResMut<res::Settings>,
+) {
+    // The options menu toggles it with its own keys
+    if *state == res::GameState::Options {
+        return;
+    }
+
+    let sources = InputSources {
+        keyboard: &keyboard_input,
+        mouse: &mouse_button_input,
+        gamepad_buttons: &gamepad_buttons,
+    };
+
+    if input_map.just_pressed(InputAction::ToggleTrajectory, &sources, InputOwner::ANY) {
+        settings.show_trajectory = !settings.show_trajectory;
+        settings.save();
+    }
+}
+
+/// Place the dots along the arc a projectile fired now would follow,
+/// from the crosshair with the weapon's muzzle speed and gravity
+fn trajectory_system(
+    state: Res<res::GameState>,
+    settings: Res<res::Settings>,
+    gravity: Res<physics::Gravity>,
+    players: Query<(With<actor::Player, &Transform>, &actor::Weapon)>,
+    crosshairs: Query<With<actor::Crosshair, &Transform>>,
+    mut dots: Query<(&TrajectoryDot, &mut Transform, &mut Draw)>,
+) {
+    let mut aim = None;
+    if settings.show_trajectory && state.is_playing() {
+        for (player_transform, weapon) in &mut players.iter() {
+            for crosshair_transform in &mut crosshairs.iter() {
+                let origin = crosshair_transform.translation.truncate();
+                let direction = util::get_direction(&player_transform.translation.truncate(), &origin);
+                if direction == Vec2::zero() {
+                    continue;
+                }
+
+                aim = Some((origin, direction.normalize() * weapon.muzzle_speed, weapon.spread));
+            }
+        }
+    }
+
+    let (origin, velocity, spread) = match aim {
+        Some(aim) => aim,
+        None => {
+            for (_, _, mut draw) in dots.iter_mut() {
+                draw.is_visible = false;
+            }
+            return;
+        }
+    };
+
+    // Spread is rolled per axis, the widest arcs sit across the aim direction
+    let across = Vec2::new(-velocity.y(), velocity.x()).normalize() * spread;
+
+    for (dot, mut transform, mut draw) in dots.iter_mut() {
+        let velocity = match dot.arc {
+            Arc::Center => velocity,
+            Arc::LowerBound => velocity - across,
+            Arc::UpperBound => velocity + across,
+        };
+
+        let t = (dot.index + 1) as f32 * DOT_TIME_STEP;
+        let position = origin + velocity * t - Vec2::unit_y() * gravity.0 * t * t / 2.;
+
+        transform.translation = position.extend(2.);
+        draw.is_visible = spread > 0. || dot.arc == Arc::Center;
+    }
+}
@@ -0,0 +1,152 @@
+use bevy::prelude::*;
+use bevy::sprite::collide_aabb::collide;
+
+use crate::comp::{actor, physics, stats};
+use crate::res;
+
+pub struct WeaponPlugin;
+
+impl Plugin for WeaponPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(setup_tank_ui_system.system())
+            .add_system(weapon_system.system())
+            .add_system(reset_weapon_system.system())
+            .add_system(tank_bar_system.system())
+            .add_system(weapon_text_system.system());
+    }
+}
+
+struct TankBar;
+
+struct WeaponText;
+
+fn setup_tank_ui_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    top: Val::Px(36.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(150.), Val::Px(10.)),
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0., 0., 0., 0.4).into()),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(NodeComponents {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                        ..Default::default()
+                    },
+                    material: materials.add(res::Colors::WATER.into()),
+                    ..Default::default()
+                })
+                .with(TankBar);
+        })
+        .spawn(TextComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(170.),
+                    top: Val::Px(32.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                value: String::new(),
+                font: asset_server.load(res::FONT),
+                style: TextStyle {
+                    font_size: 14.,
+                    color: Color::WHITE,
+                    ..Default::default()
+                },
+            },
+            ..Default::default()
+        })
+        .with(WeaponText);
+}
+
+/// Tick weapon cooldowns and refill tanks over time or at refill stations
+fn weapon_system(
+    state: Res<res::GameState>,
+    time: Res<Time>,
+    mut query: Query<(&mut actor::Weapon, &Transform, &physics::ColliderBox)>,
+    stations: Query<(&stats::RefillStation, &Transform, &physics::ColliderBox)>,
+) {
+    if !state.is_playing() {
+        return;
+    }
+
+    for (mut weapon, transform, body) in query.iter_mut() {
+        weapon.cooldown.tick(time.delta_seconds);
+
+        let mut refill_rate = weapon.refill_rate;
+        for (station, station_transform, station_body) in &mut stations.iter() {
+            let at_station = collide(
+                transform.translation,
+                body.get_size(),
+                station_transform.translation,
+                station_body.get_size(),
+            ).is_some();
+
+            if at_station {
+                refill_rate += station.0;
+            }
+        }
+
+        weapon.refill(refill_rate * time.delta_seconds);
+    }
+}
+
+/// Hand back the starting weapon with a full tank and clear the shots still in flight
+fn reset_weapon_system(
+    mut commands: Commands,
+    mut restart_event_reader: Local<EventReader<res::RestartEvent>>,
+    restart_events: Res<Events<res::RestartEvent>>,
+    mut query: Query<&mut actor::Weapon>,
+    projectiles: Query<With<actor::Projectile, Entity>>,
+) {
+    if restart_event_reader.iter(&restart_events).next().is_none() {
+        return;
+    }
+
+    for mut weapon in query.iter_mut() {
+        *weapon = actor::Weapon::default();
+    }
+
+    for entity in projectiles.iter() {
+        commands.despawn(entity);
+    }
+}
+
+fn tank_bar_system(
+    players: Query<With<actor::Player, &actor::Weapon>>,
+    mut query: Query<With<TankBar, &mut Style>>,
+) {
+    for weapon in &mut players.iter() {
+        for mut style in query.iter_mut() {
+            style.size.width = Val::Percent(weapon.get_tank_fraction() * 100.);
+        }
+    }
+}
+
+fn weapon_text_system(
+    players: Query<With<actor::Player, &actor::Weapon>>,
+    mut query: Query<With<WeaponText, &mut Text>>,
+) {
+    for weapon in &mut players.iter() {
+        for mut text in query.iter_mut() {
+            text.value = weapon.kind.name().to_string();
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use bevy::{
+    prelude::*,
+    type_registry::TypeUuid,
+};
+use serde::Deserialize;
+use crate::comp::{actor, physics, stats};
+use crate::ron_asset::{self, RonAsset, RonAssetLoader};
+
+/// Movement and physics values loaded from `movement.tuning`.
+/// Also inserted as a resource holding the values currently in use.
+/// Values missing from the file keep their defaults, so older files still load.
+#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[uuid = "a3c4e6f1-8d2b-4b7a-9e15-6f0c2d8b1a47"]
+#[serde(default)]
+pub struct Tuning {
+    pub movement_accel: f32,
+    pub movement_max: f32,
+    pub jump_force: f32,
+    pub drag: f32,
+    pub gravity: f32,
+    /// Extra gravity while falling
+    pub fall_multiplier: f32,
+    /// Extra gravity while rising without holding jump
+    pub low_jump_multiplier: f32,
+    /// Horizontal and vertical multiplier of the jump force when wall jumping
+    pub wall_jump_multiplier: (f32, f32),
+    /// Seconds after walking off a ledge during which jumping still works
+    pub coyote_time: f32,
+    /// Seconds a jump pressed before landing is remembered
+    pub jump_buffer_time: f32,
+    /// Max fall speed while pressing into a wall
+    pub wall_slide_speed: f32,
+    /// Seconds the player keeps holding a wall after pulling away from it
+    pub wall_stick_time: f32,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            movement_accel: 100.,
+            movement_max: 200.,
+            jump_force: 200.,
+            drag: 1.85,
+            gravity: 9.82 * 40.,
+            fall_multiplier: 2.5,
+            low_jump_multiplier: 2.,
+            wall_jump_multiplier: (1.4, 1.2),
+            coyote_time: 0.1,
+            jump_buffer_time: 0.12,
+            wall_slide_speed: 60.,
+            wall_stick_time: 0.15,
+        }
+    }
+}
+
+impl RonAsset for Tuning {
+    const EXTENSIONS: &'static [&'static str] = &["tuning"];
+}
+
+/// Keeps the tuning asset loaded
+pub struct TuningHandle(pub Handle<Tuning>);
+
+pub struct TuningPlugin;
+
+impl Plugin for TuningPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let tuning = Tuning::default();
+
+        app.add_asset::<Tuning>()
+            .init_asset_loader::<RonAssetLoader<Tuning>>()
+            .add_resource(physics::Gravity(tuning.gravity))
+            .add_resource(tuning)
+            .add_startup_system(load_tuning_system.system())
+            .add_system(apply_tuning_system.system());
+    }
+}
+
+fn load_tuning_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    if let Err(error) = asset_server.watch_for_changes() {
+        println!("Tuning hot reload disabled: {:?}", error);
+    }
+
+    let handle: Handle<Tuning> = asset_server.load("movement.tuning");
+    commands.insert_resource(TuningHandle(handle));
+}
+
+/// Apply the tuning file when loaded and every time it changes on disk
+fn apply_tuning_system(
+    mut event_reader: Local<EventReader<AssetEvent<Tuning>>>,
+    events: Res<Events<AssetEvent<Tuning>>>,
+    assets: Res<Assets<Tuning>>,
+    mut tuning: ResMut<Tuning>,
+    mut gravity: ResMut<physics::Gravity>,
+    mut query: Query<(
+        With<actor::Player, &mut stats::MovementSpeed>,
+        &mut stats::JumpForce,
+        &mut physics::Drag,
+        Option<&mut stats::WallStickTimer>,
+    )>,
+) {
+    for handle in event_reader.iter(&events).filter_map(ron_asset::loaded_handle) {
+        if let Some(loaded) = assets.get(handle) {
+            *tuning = loaded.clone();
+        } else {
+            continue;
+        }
+
+        gravity.0 = tuning.gravity;
+
+        for (mut speed, mut jump_force, mut drag, wall_stick) in query.iter_mut() {
+            speed.accel = tuning.movement_accel;
+            speed.max = tuning.movement_max;
+            jump_force.0 = tuning.jump_force;
+            drag.0 = tuning.drag;
+
+            if let Some(mut wall_stick) = wall_stick {
+                wall_stick.0.duration = tuning.wall_stick_time;
+            }
+        }
+    }
+}
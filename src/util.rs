@@ -1,4 +1,7 @@
 use crate::bevy::prelude::*;
+use bevy::render::camera::WindowOrigin;
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::PathBuf;
 
 // due to not being able to access windows from a `startup_system`
 // fixed values will be needed for screen size during startup
@@ -6,6 +9,17 @@ use crate::bevy::prelude::*;
 pub const SCR_WIDTH: f32 = 800.0;
 pub const SCR_HEIGHT: f32 = 600.0;
 
+/// Area the camera can show in the arena mode, min and max corners. It is the
+/// size of the view, so the camera stays put in the arena
+pub fn arena_bounds() -> (Vec2, Vec2) {
+    (
+        Vec2::new(-SCR_WIDTH / 2., -SCR_HEIGHT / 2.),
+        Vec2::new(SCR_WIDTH / 2., SCR_HEIGHT / 2.),
+    )
+}
+
+const DATA_DIR_NAME: &str = "oh-no-lava";
+
 pub fn get_distance(a: &Vec2, b: &Vec2) -> f32 {
     (a.x().powi(2) - b.x().powi(2) + a.y().powi(2) - b.y().powi(2)).sqrt()
 }
@@ -21,3 +35,96 @@ pub fn get_window_size(windows: Res<Windows>) -> Size {
         Size::new(SCR_WIDTH, SCR_HEIGHT)
     }
 }
+
+/// Convert a window position, origin at the bottom left, into world space
+/// as seen through a camera
+pub fn screen_to_world(
+    screen_position: Vec2,
+    window_size: Size,
+    camera_transform: &Transform,
+    projection: &OrthographicProjection,
+) -> Vec2 {
+    let position = match projection.window_origin {
+        WindowOrigin::Center => screen_position - Vec2::new(window_size.width / 2., window_size.height / 2.),
+        WindowOrigin::BottomLeft => screen_position,
+    };
+
+    let world = camera_transform.compute_matrix() * position.extend(0.).extend(1.);
+    world.truncate().truncate()
+}
+
+/// Path to a file in the user's data directory, the directory is created if missing
+pub fn get_data_path(file_name: &str) -> Option<PathBuf> {
+    let dir = dirs::data_dir()?.join(DATA_DIR_NAME);
+    std::fs::create_dir_all(&dir).ok()?;
+    Some(dir.join(file_name))
+}
+
+/// Read a RON file from the user's data directory, falling back to the default
+/// when it is missing or can't be parsed
+pub fn load_data<T: DeserializeOwned + Default>(file_name: &str) -> T {
+    get_data_path(file_name)
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| ron::de::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Write a value as RON to the user's data directory
+pub fn save_data<T: Serialize>(file_name: &str, value: &T) {
+    write_data(file_name, ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()));
+}
+
+/// Write a value as RON on a single line, for large files like replays
+pub fn save_data_compact<T: Serialize>(file_name: &str, value: &T) {
+    write_data(file_name, ron::ser::to_string(value));
+}
+
+fn write_data(file_name: &str, serialized: ron::Result<String>) {
+    let path = match get_data_path(file_name) {
+        Some(path) => path,
+        None => {
+            println!("Could not find a data directory for {}", file_name);
+            return;
+        }
+    };
+
+    match serialized {
+        Ok(content) => {
+            if let Err(error) = std::fs::write(&path, content) {
+                println!("Failed to save {:?}: {}", path, error);
+            }
+        },
+        Err(error) => println!("Failed to serialize {}: {}", file_name, error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_center_maps_to_the_camera_position() {
+        let camera = Transform::from_translation(Vec3::new(100., -50., 0.));
+        let world = screen_to_world(
+            Vec2::new(400., 300.),
+            Size::new(800., 600.),
+            &camera,
+            &OrthographicProjection::default(),
+        );
+
+        assert!((world - Vec2::new(100., -50.)).length() < 1e-4);
+    }
+
+    #[test]
+    fn screen_offsets_follow_the_camera_scale() {
+        let camera = Transform::from_scale(Vec3::one() * 2.);
+        let world = screen_to_world(
+            Vec2::new(0., 0.),
+            Size::new(800., 600.),
+            &camera,
+            &OrthographicProjection::default(),
+        );
+
+        assert!((world - Vec2::new(-800., -600.)).length() < 1e-4);
+    }
+}